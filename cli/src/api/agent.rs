@@ -1,49 +1,193 @@
-use std::io::{self, Write};
 use std::sync::Arc;
 
-use axum::extract::Json;
+use agent_api::types::info::*;
+use agent_api::types::inquiry::*;
+use agent_api::types::mcp::McpServer;
+use agent_api::types::session::SessionState;
+use agent_api::types::task::*;
+use axum::extract::{Json, Query};
 use axum::http::StatusCode;
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::Extension;
 use axum::Router;
-use serde::Deserialize;
-
-use agent_api::types::task::*;
 
-use crate::api::{AppState, TaskOutcome};
+use crate::api::artifacts::upload_artifact;
+use crate::api::{mcp, AppState, TaskOutcome};
+use crate::events::{Event, LogStream};
 
-#[derive(Deserialize)]
-pub struct InquiryPayload {
-    pub inquiry: String,
-}
+mod tests;
 
 pub fn router() -> Router {
     Router::new()
+        .route("/agent/info", get(runtime_info))
         .route("/agent/task", get(task_info))
+        .route("/agent/task/status", get(task_status))
+        .route("/agent/task/messages", get(task_messages))
+        .route("/agent/task/progress", post(task_progress))
         .route("/agent/task/complete", post(task_complete))
         .route("/agent/task/fail", post(task_fail))
         .route("/agent/inquiry", post(inquiry))
+        .route("/agent/session", get(session).merge(put(save_session)))
+        .route("/agent/artifacts", post(upload_artifact))
 }
 
-pub async fn task_info(Extension(state): Extension<Arc<AppState>>) -> Json<Task> {
+/// Lets agents check which parts of the API this runtime supports.
+#[utoipa::path(get, path = "/agent/info", tag = "runtime", responses((status = 200, description = "API version and capabilities of the runtime", body = RuntimeInfo)))]
+pub async fn runtime_info() -> Json<RuntimeInfo> {
+    Json(RuntimeInfo {
+        api_version: API_VERSION,
+        runtime: format!("minion {}", env!("CARGO_PKG_VERSION")),
+        capabilities: Capabilities {
+            inquiry: true,
+            progress: true,
+            messages: true,
+            session: true,
+            cancellation: true,
+            artifacts: true,
+        },
+    })
+}
+
+/// The task of the agent. Fetching a queued task claims it.
+#[utoipa::path(get, path = "/agent/task", tag = "task", params(TaskQuery), responses((status = 200, description = "The task", body = Task)))]
+pub async fn task_info(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<TaskQuery>,
+) -> Json<Task> {
+    let status = claim_task(
+        &mut state.task_status.lock().unwrap(),
+        &mut state.task_claim.lock().unwrap(),
+        query.claim,
+    );
     let response = Task {
-        status: TaskStatus::Running,
+        status,
         description: state.ctx.task_description.clone(),
         git_user_name: state.ctx.git_user_name.clone(),
         git_user_email: state.ctx.git_user_email.clone(),
         git_repo_url: state.ctx.git_repo_url.clone(),
         git_branch: state.ctx.git_branch.clone(),
+        permission_policy: state.ctx.permission_policy.clone(),
+        mcp_servers: state
+            .ctx
+            .mcp_servers
+            .iter()
+            .map(|server| proxied_mcp_server(&state, server))
+            .collect(),
+        mode: state.ctx.mode.clone(),
+        model: state.ctx.model.clone(),
+        attachments: state.ctx.attachments.clone(),
+        references: state.ctx.references.clone(),
+        acceptance_criteria: state.ctx.acceptance_criteria.clone(),
     };
 
     Json(response)
 }
 
+/// Claim the task if it is queued and return the status to report to the agent. A repeated
+/// claim, e.g. a retried request whose response was lost, gets the task as it was claimed.
+fn claim_task(
+    status: &mut TaskStatus,
+    claimed_by: &mut Option<String>,
+    claim: Option<String>,
+) -> TaskStatus {
+    match *status {
+        TaskStatus::Queued => {
+            *status = TaskStatus::Running;
+            *claimed_by = claim;
+            TaskStatus::Queued
+        }
+        TaskStatus::Running if claim.is_some() && claim == *claimed_by => TaskStatus::Queued,
+        current => current,
+    }
+}
+
+/// The status of the task. Unlike `GET /agent/task`, this does not claim a queued task and is
+/// cheap enough to poll, e.g. to notice a cancellation.
+#[utoipa::path(get, path = "/agent/task/status", tag = "task", responses((status = 200, description = "The status of the task", body = TaskState)))]
+pub async fn task_status(Extension(state): Extension<Arc<AppState>>) -> Json<TaskState> {
+    Json(TaskState {
+        status: *state.task_status.lock().unwrap(),
+    })
+}
+
+/// Messages of the user, sent while the agent is working on the task.
+#[utoipa::path(
+    get,
+    path = "/agent/task/messages",
+    tag = "task",
+    params(TaskMessagesQuery),
+    responses((status = 200, description = "Messages in the order they were sent", body = Vec<TaskMessage>))
+)]
+pub async fn task_messages(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<TaskMessagesQuery>,
+) -> Json<Vec<TaskMessage>> {
+    let messages = state.messages.lock().unwrap();
+    let after = query.after.unwrap_or(0);
+    let response = messages
+        .iter()
+        .zip(1u64..)
+        .skip_while(|(_, id)| *id <= after)
+        .map(|(message, id)| TaskMessage {
+            id,
+            message: message.clone(),
+        })
+        .collect();
+    Json(response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/task/progress",
+    tag = "task",
+    request_body = TaskProgress,
+    responses(
+        (status = 200, description = "The progress was reported to the user"),
+        (status = 400, description = "The percentage is not between 0 and 100")
+    )
+)]
+pub async fn task_progress(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(body): Json<TaskProgress>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    validate_progress(&body)?;
+    state
+        .ctx
+        .console
+        .print(LogStream::Stderr, &render_progress(&body));
+    state.ctx.events.publish(Event::Progress(body));
+    Ok(StatusCode::OK)
+}
+
+fn validate_progress(progress: &TaskProgress) -> Result<(), (StatusCode, String)> {
+    match progress.percent {
+        Some(percent) if percent > 100 => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Progress of {percent}% is not between 0 and 100"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/task/complete",
+    tag = "task",
+    request_body = TaskComplete,
+    responses((status = 200, description = "The task is completed"))
+)]
 pub async fn task_complete(
     Extension(state): Extension<Arc<AppState>>,
     Json(body): Json<TaskComplete>,
 ) -> StatusCode {
-    println!("\nTask completed");
-    println!("{}", body.description);
+    state.ctx.console.print(
+        LogStream::Stderr,
+        &format!("\nTask completed\n{}\n", body.description),
+    );
+    *state.task_status.lock().unwrap() = TaskStatus::Completed;
+    state.ctx.events.publish(Event::TaskCompleted {
+        description: body.description,
+    });
 
     if let Some(tx) = state.shutdown_tx.lock().await.take() {
         tx.send(TaskOutcome::Completed)
@@ -57,12 +201,25 @@ pub async fn task_complete(
     StatusCode::OK
 }
 
+#[utoipa::path(
+    post,
+    path = "/agent/task/fail",
+    tag = "task",
+    request_body = TaskFailure,
+    responses((status = 200, description = "The task is failed"))
+)]
 pub async fn task_fail(
     Extension(state): Extension<Arc<AppState>>,
     Json(body): Json<TaskFailure>,
 ) -> StatusCode {
-    println!("Task failed");
-    println!("{}", body.description);
+    state.ctx.console.print(
+        LogStream::Stderr,
+        &format!("Task failed\n{}\n", body.description),
+    );
+    *state.task_status.lock().unwrap() = TaskStatus::Failed;
+    state.ctx.events.publish(Event::TaskFailed {
+        description: body.description,
+    });
 
     if let Some(tx) = state.shutdown_tx.lock().await.take() {
         tx.send(TaskOutcome::Failure)
@@ -76,26 +233,94 @@ pub async fn task_fail(
     StatusCode::OK
 }
 
-/// Send an inquiry to the user and await its answer.
-/// Agents use this endpoint to request clarification on their tasks.
-pub async fn inquiry(Json(request): Json<InquiryPayload>) -> Json<String> {
-    let question = request.inquiry.clone();
+/// The agent's session, if it saved one. An agent that restarts in the same container uses it
+/// to resume its work.
+#[utoipa::path(
+    get,
+    path = "/agent/session",
+    tag = "session",
+    responses((status = 200, description = "The saved session, or null", body = Option<SessionState>))
+)]
+pub async fn session(Extension(state): Extension<Arc<AppState>>) -> Json<Option<SessionState>> {
+    Json(state.session.lock().unwrap().clone())
+}
 
-    println!("Agent is asking: {question}");
+#[utoipa::path(
+    put,
+    path = "/agent/session",
+    tag = "session",
+    request_body = SessionState,
+    responses((status = 200, description = "The session was saved"))
+)]
+pub async fn save_session(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(body): Json<SessionState>,
+) -> StatusCode {
+    *state.session.lock().unwrap() = Some(body);
+    StatusCode::OK
+}
 
-    let answer = (tokio::task::spawn_blocking(move || {
-        print!("Your answer: ");
-        io::stdout().flush().ok();
+/// Send an inquiry to the user and await its answer.
+/// Agents use this endpoint to request clarification on their tasks.
+///
+/// Depending on the inquiry policy of the run, inquiries are answered by the user (on the
+/// terminal or the dashboard), answered automatically or rejected.
+#[utoipa::path(
+    post,
+    path = "/agent/inquiry",
+    tag = "inquiry",
+    request_body = Inquiry,
+    responses(
+        (status = 200, description = "The answer of the user", body = InquiryAnswer),
+        (status = 503, description = "Inquiries are not answered during this run")
+    )
+)]
+pub async fn inquiry(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(request): Json<Inquiry>,
+) -> Result<Json<InquiryAnswer>, (StatusCode, String)> {
+    match state
+        .inquiries
+        .ask(&state.ctx.inquiry_policy, request.inquiry)
+        .await
+    {
+        Some(answer) => Ok(Json(InquiryAnswer { answer })),
+        None => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Inquiries are not answered during this run".to_owned(),
+        )),
+    }
+}
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_ok() {
-            input.trim().to_string()
-        } else {
-            String::new()
+/// HTTP servers are reached through the host API, since their URLs are only valid on the host.
+fn proxied_mcp_server(state: &AppState, server: &McpServer) -> McpServer {
+    match server {
+        McpServer::Stdio { .. } => server.clone(),
+        McpServer::Http { name, .. } => {
+            let url = mcp::proxy_url(&state.ctx.agent_api_base_url, name);
+            let authorization = format!("Bearer {}", state.ctx.agent_api_key);
+            McpServer::Http {
+                name: name.clone(),
+                url,
+                headers: [("Authorization".to_owned(), authorization)].into(),
+            }
         }
-    })
-    .await)
-        .unwrap_or_default();
+    }
+}
 
-    Json(answer)
+/// Render a compact status line, followed by the plan (if any).
+fn render_progress(progress: &TaskProgress) -> String {
+    let mut out = match progress.percent {
+        Some(percent) => format!("\n[progress {percent:>3}%] {}\n", progress.message),
+        None => format!("\n[progress] {}\n", progress.message),
+    };
+    for entry in &progress.plan {
+        let marker = match entry.status {
+            PlanEntryStatus::Pending => ' ',
+            PlanEntryStatus::InProgress => '>',
+            PlanEntryStatus::Completed => 'x',
+        };
+        out.push_str(&format!("  [{marker}] {}\n", entry.title));
+    }
+    out
 }
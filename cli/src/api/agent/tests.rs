@@ -0,0 +1,76 @@
+//! Tests for claiming the task and reporting progress.
+#![cfg(test)]
+
+use agent_api::types::task::{TaskProgress, TaskStatus};
+use axum::http::StatusCode;
+
+use super::{claim_task, validate_progress};
+
+#[test]
+fn first_fetch_claims_the_task() {
+    let mut status = TaskStatus::Queued;
+    let mut claimed_by = None;
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, Some("a".to_owned())),
+        TaskStatus::Queued
+    );
+    assert_eq!(status, TaskStatus::Running);
+    assert_eq!(claimed_by.as_deref(), Some("a"));
+
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, Some("b".to_owned())),
+        TaskStatus::Running
+    );
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, None),
+        TaskStatus::Running
+    );
+}
+
+#[test]
+fn repeated_claim_keeps_the_task() {
+    // The response to the first fetch was lost, so the agent fetches again with its claim.
+    let mut status = TaskStatus::Queued;
+    let mut claimed_by = None;
+    claim_task(&mut status, &mut claimed_by, Some("a".to_owned()));
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, Some("a".to_owned())),
+        TaskStatus::Queued
+    );
+    assert_eq!(status, TaskStatus::Running);
+}
+
+#[test]
+fn fetch_without_claim_cannot_be_repeated() {
+    let mut status = TaskStatus::Queued;
+    let mut claimed_by = None;
+    claim_task(&mut status, &mut claimed_by, None);
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, None),
+        TaskStatus::Running
+    );
+}
+
+#[test]
+fn cancelled_task_is_not_claimed() {
+    let mut status = TaskStatus::Cancelled;
+    let mut claimed_by = None;
+    assert_eq!(
+        claim_task(&mut status, &mut claimed_by, Some("a".to_owned())),
+        TaskStatus::Cancelled
+    );
+    assert_eq!(claimed_by, None);
+}
+
+#[test]
+fn progress_beyond_100_percent_is_rejected() {
+    let progress = |percent| TaskProgress {
+        message: "Working".to_owned(),
+        percent,
+        plan: Vec::new(),
+    };
+    assert!(validate_progress(&progress(None)).is_ok());
+    assert!(validate_progress(&progress(Some(100))).is_ok());
+    let (status, _) = validate_progress(&progress(Some(101))).unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,152 @@
+//! Files the agent produces for the user that do not belong in git, e.g. test reports,
+//! screenshots or benchmark output.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use agent_api::types::artifact::{Artifact, ArtifactQuery};
+use axum::body::Body;
+use axum::extract::{Json, Query};
+use axum::http::StatusCode;
+use axum::Extension;
+use futures::StreamExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::api::AppState;
+use crate::events::{Event, LogStream};
+
+/// Maximum size of a single artifact.
+const MAX_ARTIFACT_SIZE: u64 = 100 * 1024 * 1024;
+/// Maximum size of all artifacts of a task.
+const MAX_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Store a file for the user. The body of the request is the content of the file.
+#[utoipa::path(
+    post,
+    path = "/agent/artifacts",
+    tag = "artifacts",
+    params(ArtifactQuery),
+    request_body(content = String, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "The artifact was stored", body = Artifact),
+        (status = 400, description = "The name is not a valid file name"),
+        (status = 413, description = "The artifact exceeds the size limits")
+    )
+)]
+pub async fn upload_artifact(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(query): Query<ArtifactQuery>,
+    body: Body,
+) -> Result<Json<Artifact>, (StatusCode, String)> {
+    let name = query.name;
+    validate_name(&name).map_err(|reason| (StatusCode::BAD_REQUEST, reason.to_owned()))?;
+
+    // Uploads are stored one at a time, so that concurrent ones cannot exceed the total limit.
+    let _upload = state.artifact_uploads.lock().await;
+
+    let dir = &state.ctx.artifacts_dir;
+    let used: u64 = list(dir)
+        .map_err(internal_error)?
+        .iter()
+        .filter(|artifact| artifact.name != name)
+        .map(|artifact| artifact.size)
+        .sum();
+    let limit = MAX_ARTIFACT_SIZE.min(MAX_TOTAL_SIZE.saturating_sub(used));
+
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(internal_error)?;
+    // Write to a hidden file first, so that a failed upload leaves no partial artifact.
+    let partial_path = dir.join(format!(".{name}.partial"));
+    let size = match write_limited(&partial_path, body, limit).await {
+        Ok(size) => size,
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(err);
+        }
+    };
+    tokio::fs::rename(&partial_path, dir.join(&name))
+        .await
+        .map_err(internal_error)?;
+
+    state.ctx.console.print(
+        LogStream::Stderr,
+        &format!("\n[artifact] {name} ({size} bytes)\n"),
+    );
+    state.ctx.events.publish(Event::Artifact {
+        name: name.clone(),
+        size,
+    });
+    Ok(Json(Artifact { name, size }))
+}
+
+/// The artifacts in `dir`, sorted by name. A missing directory has none.
+pub fn list(dir: &Path) -> io::Result<Vec<Artifact>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut artifacts = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        // Hidden files are uploads in progress.
+        if !metadata.is_file() || name.starts_with('.') {
+            continue;
+        }
+        artifacts.push(Artifact {
+            name,
+            size: metadata.len(),
+        });
+    }
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(artifacts)
+}
+
+/// Artifacts are stored flat in the task's directory, so names must not contain directories.
+fn validate_name(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() || name.len() > 255 {
+        return Err("The name must have between 1 and 255 bytes");
+    }
+    if name.starts_with('.') {
+        return Err("The name must not start with a dot");
+    }
+    if name.contains(['/', '\\', '\0']) {
+        return Err("The name must not contain path separators");
+    }
+    Ok(())
+}
+
+/// Stream `body` into a new file at `path`. Fails once more than `limit` bytes were received.
+async fn write_limited(path: &Path, body: Body, limit: u64) -> Result<u64, (StatusCode, String)> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(internal_error)?;
+    let mut stream = body.into_data_stream();
+    let mut size = 0u64;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+        size += chunk.len() as u64;
+        if size > limit {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "Artifacts are limited to {MAX_ARTIFACT_SIZE} bytes each and \
+                     {MAX_TOTAL_SIZE} bytes per task"
+                ),
+            ));
+        }
+        file.write_all(&chunk).await.map_err(internal_error)?;
+    }
+    file.flush().await.map_err(internal_error)?;
+    Ok(size)
+}
+
+fn internal_error(err: io::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
@@ -2,19 +2,24 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use axum::http::{HeaderMap, HeaderName, HeaderValue};
-use llm_proxy::{ProxyConfig, ProxyError, ProxyResult};
+use llm_proxy::{ProxyConfig, ProxyError, ProxyResult, ResponseCache};
 
 use llm_proxy::{CompletionRequest, ForwardConfig};
 
+use crate::api::metrics::Metrics;
 use crate::context::Context;
+use crate::events::Event;
 
-pub fn router(ctx: Arc<Context>) -> axum::Router {
-    llm_proxy::scope(TheProxyConfig { ctx })
+mod tests;
+
+pub fn router(ctx: Arc<Context>, metrics: Arc<Metrics>) -> axum::Router {
+    llm_proxy::scope(TheProxyConfig { ctx, metrics })
 }
 
 #[derive(Clone)]
 struct TheProxyConfig {
     ctx: Arc<Context>,
+    metrics: Arc<Metrics>,
 }
 
 #[async_trait]
@@ -76,12 +81,25 @@ impl ProxyConfig for TheProxyConfig {
         })
     }
 
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        self.ctx.llm_response_cache.as_ref()
+    }
+
     async fn inspect_interaction(
         &self,
         _ctx: &Self::Context,
         request: &CompletionRequest,
         response: Option<serde_json::Value>,
     ) {
+        if let Some(usage) = response.as_ref().and_then(|r| r.get("usage")) {
+            self.metrics.record_token_usage(usage);
+        }
+        self.publish_llm_call(
+            "chat/completions",
+            request.model.clone(),
+            request.stream.unwrap_or(false),
+            response.as_ref(),
+        );
         log::trace!("Request: {request:?}\n\nResponse: {response:?}");
     }
 
@@ -91,8 +109,68 @@ impl ProxyConfig for TheProxyConfig {
         request: &serde_json::Value,
         response: Option<serde_json::Value>,
     ) {
+        if let Some(usage) = response.as_ref().and_then(|r| r.get("usage")) {
+            self.metrics.record_token_usage(usage);
+        }
+        self.publish_llm_call(
+            "responses",
+            request
+                .get("model")
+                .and_then(|v| v.as_str())
+                .map(str::to_owned),
+            request
+                .get("stream")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            response.as_ref(),
+        );
         log::trace!("Request: {request:?}\n\nResponse: {response:?}");
     }
+
+    async fn inspect_stream_usage(&self, _ctx: &Self::Context, usage: serde_json::Value) {
+        self.metrics.record_token_usage(&usage);
+        let (prompt_tokens, completion_tokens) = token_counts(&usage);
+        self.ctx.events.publish(Event::LlmUsage {
+            prompt_tokens: prompt_tokens.unwrap_or(0),
+            completion_tokens: completion_tokens.unwrap_or(0),
+        });
+    }
+}
+
+impl TheProxyConfig {
+    fn publish_llm_call(
+        &self,
+        api: &'static str,
+        model: Option<String>,
+        streamed: bool,
+        response: Option<&serde_json::Value>,
+    ) {
+        // Streamed responses report their usage later, see `inspect_stream_usage`.
+        let (prompt_tokens, completion_tokens) = response
+            .and_then(|response| response.get("usage"))
+            .map(token_counts)
+            .unwrap_or_default();
+        self.ctx.events.publish(Event::LlmCall {
+            api,
+            model,
+            streamed,
+            prompt_tokens,
+            completion_tokens,
+        });
+    }
+}
+
+/// The prompt and completion tokens in the `usage` object of a chat completions or responses
+/// API response.
+fn token_counts(usage: &serde_json::Value) -> (Option<u64>, Option<u64>) {
+    let count = |keys: [&str; 2]| {
+        keys.iter()
+            .find_map(|key| usage.get(*key).and_then(|v| v.as_u64()))
+    };
+    (
+        count(["prompt_tokens", "input_tokens"]),
+        count(["completion_tokens", "output_tokens"]),
+    )
 }
 
 fn build_header_map(
@@ -0,0 +1,117 @@
+//! Tests for publishing the LLM calls forwarded by the proxy.
+#![cfg(test)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::routing::post;
+use axum::Router;
+use tokio::sync::oneshot;
+
+use crate::api::metrics::Metrics;
+use crate::api::{InquiryMode, InquiryPolicy};
+use crate::cli::report::summarize_events;
+use crate::config::{LLMProviderDetails, LLMRouterTable};
+use crate::console::Console;
+use crate::context::Context;
+use crate::events::{Event, EventHub};
+
+const EVENTS: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+    "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3}}\n\n",
+    "data: [DONE]\n\n",
+);
+
+/// An LLM provider that streams `EVENTS` for every completion.
+async fn upstream() -> String {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(|| async { ([(CONTENT_TYPE, "text/event-stream")], EVENTS) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+fn context(upstream: &str) -> Context {
+    let details = LLMProviderDetails {
+        api_chat_completions_endpoint: format!("{upstream}/chat/completions").parse().unwrap(),
+        api_responses_endpoint: format!("{upstream}/responses").parse().unwrap(),
+        api_models_endpoint: format!("{upstream}/models").parse().unwrap(),
+        api_key: "key".to_owned(),
+        upstream_headers: HashMap::new(),
+    };
+    Context {
+        llm_router_table: LLMRouterTable {
+            default_provider: "test".to_owned(),
+            providers: HashMap::from([("test".to_owned(), details)]),
+        },
+        llm_response_cache: None,
+        agent_api_key: "key".to_owned(),
+        task_description: "Say hi".to_owned(),
+        git_user_name: "minion[bot]".to_owned(),
+        git_user_email: "minion@localhost".to_owned(),
+        agent_api_base_url: "http://127.0.0.1:9/api/".parse().unwrap(),
+        git_repo_url: "http://127.0.0.1:9/git/".parse().unwrap(),
+        git_branch: "main".to_owned(),
+        git_repo_path: "/nonexistent".into(),
+        artifacts_dir: "/nonexistent".into(),
+        events: Arc::new(EventHub::new()),
+        console: Arc::new(Console::stderr_only()),
+        inquiry_policy: InquiryPolicy {
+            mode: InquiryMode::Fail,
+            timeout: None,
+            default_answer: String::new(),
+        },
+        permission_policy: None,
+        mcp_servers: Vec::new(),
+        mode: None,
+        model: None,
+        attachments: Vec::new(),
+        references: Vec::new(),
+        acceptance_criteria: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn streamed_usage_is_counted() {
+    let ctx = Arc::new(context(&upstream().await));
+    let (_, events_rx) = ctx.events.subscribe();
+    let app = super::router(ctx.clone(), Arc::new(Metrics::new()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let body = reqwest::Client::new()
+        .post(format!("http://{addr}/chat/completions"))
+        .json(&serde_json::json!({"model": "gpt", "stream": true, "messages": []}))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    // The client did not ask for the usage, so it is only counted.
+    assert!(!body.contains("usage"), "{body}");
+
+    let (stop_tx, stop_rx) = oneshot::channel();
+    stop_tx.send(()).unwrap();
+    let summary = summarize_events(events_rx, stop_rx).await;
+    assert_eq!(summary.usage.llm_calls, 1);
+    assert_eq!(summary.usage.prompt_tokens, 12);
+    assert_eq!(summary.usage.completion_tokens, 3);
+
+    let (history, _) = ctx.events.subscribe();
+    assert!(matches!(
+        history.as_slice(),
+        [
+            Event::LlmCall { streamed: true, .. },
+            Event::LlmUsage {
+                prompt_tokens: 12,
+                completion_tokens: 3
+            }
+        ]
+    ));
+}
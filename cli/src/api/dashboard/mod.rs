@@ -0,0 +1,145 @@
+//! A small web UI for monitoring a running task and answering the agent's inquiries.
+//!
+//! The dashboard is served on a separate listener bound to localhost, so it is not reachable
+//! from the agent's container. Every request needs to carry the dashboard token, either as a
+//! `token` query parameter or as a Bearer token.
+
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Json, Path};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{self, KeepAlive, Sse};
+use axum::response::{Html, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::api::inquiries::InquiryInfo;
+use crate::api::AppState;
+
+/// Where and how to serve the dashboard.
+pub struct DashboardConfig {
+    /// Listener bound to a loopback address.
+    pub listener: TcpListener,
+    /// Token required to access the dashboard.
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+struct AnswerPayload {
+    answer: String,
+}
+
+#[derive(Deserialize)]
+struct MessagePayload {
+    message: String,
+}
+
+pub fn router(token: String) -> Router {
+    let token = Arc::new(token);
+    Router::new()
+        .route("/", get(index))
+        .route("/events", get(events))
+        .route("/inquiries", get(inquiries))
+        .route("/inquiries/{id}", post(answer_inquiry))
+        .route("/messages", post(send_message))
+        .route("/cancel", post(cancel_task))
+        .route_layer(middleware::from_fn(move |req, next| {
+            let token = token.clone();
+            async move { token_auth_middleware(&token, req, next).await }
+        }))
+}
+
+async fn token_auth_middleware(
+    token: &str,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let query_token = req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    let header_token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_owned);
+
+    if query_token.or(header_token).as_deref() == Some(token) {
+        Ok(next.run(req).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+async fn index() -> Html<&'static str> {
+    Html(include_str!("index.html"))
+}
+
+/// Stream all past and future run events as server-sent events.
+async fn events(
+    Extension(state): Extension<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let (history, rx) = state.ctx.events.subscribe();
+
+    let live = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Dashboard skipped {skipped} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let stream = stream::iter(history).chain(live).map(|event| {
+        Ok(sse::Event::default()
+            .json_data(&event)
+            .unwrap_or_else(|_| sse::Event::default()))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn inquiries(Extension(state): Extension<Arc<AppState>>) -> Json<Vec<InquiryInfo>> {
+    Json(state.inquiries.pending())
+}
+
+async fn answer_inquiry(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<u64>,
+    Json(payload): Json<AnswerPayload>,
+) -> StatusCode {
+    if state.inquiries.answer(id, payload.answer) {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn send_message(
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<MessagePayload>,
+) -> StatusCode {
+    if payload.message.trim().is_empty() {
+        return StatusCode::BAD_REQUEST;
+    }
+    state.send_message(payload.message);
+    StatusCode::OK
+}
+
+async fn cancel_task(Extension(state): Extension<Arc<AppState>>) -> StatusCode {
+    state.cancel_task();
+    StatusCode::OK
+}
@@ -2,10 +2,13 @@ use std::sync::Arc;
 
 use axum::body::Body;
 use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
 
 use git_proxy::{BasicAuth, ForwardToLocal, ProxyBehaivor, ProxyError};
 
 use crate::api::AppState;
+use crate::events::Event;
 
 /// Validator function for Basic authentication
 pub async fn basic_auth_validator(
@@ -33,3 +36,21 @@ pub async fn basic_auth_validator(
         Err(ProxyError::unauthorized("Invalid username or password"))
     }
 }
+
+/// Middleware that publishes an event for each push handled by the git proxy.
+pub async fn push_events_middleware(req: Request<Body>, next: Next) -> Response {
+    let is_push = req.uri().path().ends_with("/git-receive-pack");
+    let state = req.extensions().get::<Arc<AppState>>().cloned();
+
+    let response = next.run(req).await;
+
+    if let (true, Some(state)) = (is_push, state) {
+        if response.status().is_success() {
+            state.ctx.events.publish(Event::GitPush {
+                git_ref: format!("refs/heads/{}", state.ctx.git_branch),
+            });
+        }
+    }
+
+    response
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::cli::editor::Editor;
+use crate::console::Console;
+use crate::events::{Event, EventHub};
+
+mod tests;
+
+/// Input that opens the user's editor to write a longer answer.
+const EDITOR_COMMAND: &str = ":edit";
+
+/// How inquiries of the agent are handled.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InquiryMode {
+    /// Ask the user on the terminal (and the dashboard, if enabled).
+    #[default]
+    Interactive,
+    /// Immediately reply with the default answer.
+    AutoAnswer,
+    /// Reject inquiries, so the agent has to proceed on its own.
+    Fail,
+}
+
+#[derive(Clone)]
+pub struct InquiryPolicy {
+    pub mode: InquiryMode,
+    /// How long to wait for the user before replying with the default answer.
+    pub timeout: Option<Duration>,
+    pub default_answer: String,
+}
+
+impl Default for InquiryPolicy {
+    fn default() -> Self {
+        Self {
+            mode: InquiryMode::default(),
+            timeout: None,
+            default_answer: "No answer is available. Proceed as you see fit.".to_owned(),
+        }
+    }
+}
+
+/// Questions of the agent that are waiting for an answer.
+///
+/// An inquiry can be answered from any frontend (e.g. the terminal or the web dashboard);
+/// the first answer wins.
+pub struct Inquiries {
+    next_id: AtomicU64,
+    pending: Mutex<BTreeMap<u64, PendingInquiry>>,
+    events: Arc<EventHub>,
+}
+
+struct PendingInquiry {
+    question: String,
+    answer_tx: oneshot::Sender<String>,
+}
+
+#[derive(Serialize)]
+pub struct InquiryInfo {
+    pub id: u64,
+    pub question: String,
+}
+
+impl Inquiries {
+    pub fn new(events: Arc<EventHub>) -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(BTreeMap::new()),
+            events,
+        }
+    }
+
+    /// Register a new inquiry. The returned receiver resolves once the inquiry is answered.
+    pub fn open(&self, question: String) -> (u64, oneshot::Receiver<String>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (answer_tx, answer_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(
+            id,
+            PendingInquiry {
+                question: question.clone(),
+                answer_tx,
+            },
+        );
+        self.events.publish(Event::Inquiry { id, question });
+        (id, answer_rx)
+    }
+
+    /// Ask the user a question as the policy prescribes and wait for the answer.
+    /// Returns `None` if inquiries are not answered during this run.
+    pub async fn ask(&self, policy: &InquiryPolicy, question: String) -> Option<String> {
+        match policy.mode {
+            InquiryMode::Interactive => {}
+            InquiryMode::AutoAnswer => return Some(policy.default_answer.clone()),
+            InquiryMode::Fail => return None,
+        }
+
+        let (id, answer_rx) = self.open(question);
+        let answer = match policy.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, answer_rx).await {
+                Ok(answer) => answer.ok(),
+                Err(_) => {
+                    // Withdraw the inquiry from all frontends.
+                    self.answer(id, policy.default_answer.clone());
+                    Some(policy.default_answer.clone())
+                }
+            },
+            None => answer_rx.await.ok(),
+        };
+        Some(answer.unwrap_or_else(|| policy.default_answer.clone()))
+    }
+
+    /// Answer a pending inquiry.
+    /// Returns `false` if there is no pending inquiry with the given ID.
+    pub fn answer(&self, id: u64, answer: String) -> bool {
+        let Some(inquiry) = self.pending.lock().unwrap().remove(&id) else {
+            return false;
+        };
+        self.events.publish(Event::InquiryAnswered {
+            id,
+            answer: answer.clone(),
+        });
+        // The agent may have given up waiting, in which case the answer is dropped.
+        let _ = inquiry.answer_tx.send(answer);
+        true
+    }
+
+    pub fn pending(&self) -> Vec<InquiryInfo> {
+        self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, inquiry)| InquiryInfo {
+                id: *id,
+                question: inquiry.question.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Show pending inquiries on the terminal, one at a time and in the order they were asked.
+///
+/// Container output is paused while an inquiry is shown. Inquiries that are answered elsewhere
+/// (e.g. on the dashboard or due to a timeout) are withdrawn from the terminal.
+pub async fn prompt_on_terminal(
+    inquiries: Arc<Inquiries>,
+    events: Arc<EventHub>,
+    console: Arc<Console>,
+) {
+    let (history, mut rx) = events.subscribe();
+    let mut queue = VecDeque::new();
+    for event in history {
+        track_inquiry(&mut queue, event);
+    }
+    let mut stdin = stdin_lines().lock().await;
+
+    loop {
+        let Some((id, question)) = queue.front().cloned() else {
+            match rx.recv().await {
+                Ok(event) => track_inquiry(&mut queue, event),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            }
+            continue;
+        };
+
+        // Lines entered while no inquiry was shown are not meant as answers.
+        while stdin.try_recv().is_ok() {}
+
+        console.pause();
+        eprintln!();
+        eprintln!("Agent is asking: {question}");
+        eprintln!("(Enter {EDITOR_COMMAND} to write your answer in an editor.)");
+
+        loop {
+            eprint!("Your answer: ");
+            io::stderr().flush().ok();
+
+            let line = tokio::select! {
+                line = stdin.recv() => Some(line),
+                _ = wait_until_answered(&mut rx, &mut queue, id) => None,
+            };
+
+            let Some(line) = line else {
+                eprintln!();
+                eprintln!("(The inquiry has been answered elsewhere.)");
+                break;
+            };
+
+            let Some(line) = line else {
+                // Stdin is closed, so inquiries cannot be answered on the terminal.
+                console.resume();
+                return;
+            };
+            let answer = if line == EDITOR_COMMAND {
+                match read_answer_from_editor().await {
+                    Some(answer) => answer,
+                    None => continue,
+                }
+            } else {
+                line
+            };
+            inquiries.answer(id, answer);
+            break;
+        }
+
+        queue.retain(|(pending_id, _)| *pending_id != id);
+        console.resume();
+    }
+}
+
+/// Keep the queue of pending inquiries up to date.
+fn track_inquiry(queue: &mut VecDeque<(u64, String)>, event: Event) {
+    match event {
+        Event::Inquiry { id, question } => queue.push_back((id, question)),
+        Event::InquiryAnswered { id, .. } => queue.retain(|(pending_id, _)| *pending_id != id),
+        _ => {}
+    }
+}
+
+/// Track inquiries until the inquiry with the given ID is answered.
+async fn wait_until_answered(
+    rx: &mut tokio::sync::broadcast::Receiver<Event>,
+    queue: &mut VecDeque<(u64, String)>,
+    id: u64,
+) {
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                let answered = matches!(event, Event::InquiryAnswered { id: answered_id, .. } if answered_id == id);
+                track_inquiry(queue, event);
+                if answered {
+                    return;
+                }
+            }
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+/// Lines read from stdin. The channel is closed once stdin is.
+///
+/// Reads of stdin block and cannot be cancelled, so they happen on a detached thread: a read that
+/// is pending when an inquiry is withdrawn must neither keep the runtime from shutting down nor
+/// hold on to the line for the next inquiry.
+fn stdin_lines() -> &'static tokio::sync::Mutex<mpsc::UnboundedReceiver<String>> {
+    static LINES: OnceLock<tokio::sync::Mutex<mpsc::UnboundedReceiver<String>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || loop {
+            let mut input = String::new();
+            match io::stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    if tx.send(input.trim().to_owned()).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        tokio::sync::Mutex::new(rx)
+    })
+}
+
+async fn read_answer_from_editor() -> Option<String> {
+    let initial_message =
+        "\n\n# Please write your answer. Lines starting with '#' will be ignored.";
+    let edited = tokio::task::spawn_blocking(move || Editor::new().edit(initial_message))
+        .await
+        .ok()?;
+    let edited = match edited {
+        Ok(Some(text)) => text,
+        Ok(None) => return None,
+        Err(err) => {
+            eprintln!("Failed to open editor: {err}");
+            return None;
+        }
+    };
+    let answer = edited
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let answer = answer.trim();
+    (!answer.is_empty()).then(|| answer.to_owned())
+}
@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::events::{Event, EventHub};
+
+use super::{track_inquiry, Inquiries, InquiryMode, InquiryPolicy};
+
+fn policy(mode: InquiryMode, timeout: Option<Duration>) -> InquiryPolicy {
+    InquiryPolicy {
+        mode,
+        timeout,
+        default_answer: "default".to_owned(),
+    }
+}
+
+#[test]
+fn pending_inquiries_are_answered_once() {
+    let inquiries = Inquiries::new(Arc::new(EventHub::new()));
+    let (first, mut first_rx) = inquiries.open("first?".to_owned());
+    let (second, _second_rx) = inquiries.open("second?".to_owned());
+
+    let pending = inquiries.pending();
+    assert_eq!(pending.len(), 2);
+    assert_eq!(
+        (pending[0].id, pending[0].question.as_str()),
+        (first, "first?")
+    );
+
+    assert!(inquiries.answer(first, "yes".to_owned()));
+    assert_eq!(first_rx.try_recv().unwrap(), "yes");
+    assert!(!inquiries.answer(first, "again".to_owned()));
+    assert_eq!(inquiries.pending()[0].id, second);
+}
+
+#[test]
+fn terminal_queue_follows_the_order_of_inquiries() {
+    let mut queue = VecDeque::new();
+    for (id, question) in [(1, "a"), (2, "b"), (3, "c")] {
+        track_inquiry(
+            &mut queue,
+            Event::Inquiry {
+                id,
+                question: question.to_owned(),
+            },
+        );
+    }
+    track_inquiry(
+        &mut queue,
+        Event::InquiryAnswered {
+            id: 2,
+            answer: "b".to_owned(),
+        },
+    );
+    track_inquiry(&mut queue, Event::TaskCancelled);
+
+    let ids: Vec<_> = queue.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, [1, 3]);
+}
+
+#[tokio::test]
+async fn modes_decide_whether_the_user_is_asked() {
+    let inquiries = Inquiries::new(Arc::new(EventHub::new()));
+
+    let auto = policy(InquiryMode::AutoAnswer, None);
+    assert_eq!(
+        inquiries.ask(&auto, "?".to_owned()).await.as_deref(),
+        Some("default")
+    );
+    let fail = policy(InquiryMode::Fail, None);
+    assert_eq!(inquiries.ask(&fail, "?".to_owned()).await, None);
+    assert!(inquiries.pending().is_empty());
+}
+
+#[tokio::test]
+async fn interactive_inquiries_wait_for_an_answer() {
+    let inquiries = Arc::new(Inquiries::new(Arc::new(EventHub::new())));
+    let interactive = policy(InquiryMode::Interactive, None);
+
+    let ask = tokio::spawn({
+        let inquiries = inquiries.clone();
+        async move { inquiries.ask(&interactive, "?".to_owned()).await }
+    });
+    let id = loop {
+        if let Some(inquiry) = inquiries.pending().first() {
+            break inquiry.id;
+        }
+        tokio::task::yield_now().await;
+    };
+    inquiries.answer(id, "from the dashboard".to_owned());
+    assert_eq!(ask.await.unwrap().as_deref(), Some("from the dashboard"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn unanswered_inquiries_time_out_with_the_default_answer() {
+    let events = Arc::new(EventHub::new());
+    let inquiries = Inquiries::new(events.clone());
+    let (_, mut rx) = events.subscribe();
+    let timeout = policy(InquiryMode::Interactive, Some(Duration::from_secs(60)));
+
+    assert_eq!(
+        inquiries.ask(&timeout, "?".to_owned()).await.as_deref(),
+        Some("default")
+    );
+    // The inquiry is withdrawn from all frontends.
+    assert!(inquiries.pending().is_empty());
+    assert!(matches!(rx.recv().await, Ok(Event::Inquiry { .. })));
+    assert!(matches!(
+        rx.recv().await,
+        Ok(Event::InquiryAnswered { answer, .. }) if answer == "default"
+    ));
+}
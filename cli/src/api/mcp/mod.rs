@@ -0,0 +1,165 @@
+//! Proxy for MCP servers that are declared by the user and reachable from the host only.
+//!
+//! The agent connects to `/api/agent/mcp/{name}` with its API key. Requests are forwarded to the
+//! server's actual URL together with the configured headers, so secrets stay on the host.
+
+use std::sync::Arc;
+
+use agent_api::types::mcp::McpServer;
+use axum::body::Body;
+use axum::extract::{Path, Request, State};
+use axum::http::header::{AUTHORIZATION, CONNECTION, CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::{Extension, Router};
+use futures::TryStreamExt;
+use url::Url;
+
+use crate::api::AppState;
+
+mod tests;
+
+/// Headers that only apply to a single connection and are not forwarded.
+const HOP_BY_HOP_HEADERS: [HeaderName; 5] = [
+    AUTHORIZATION,
+    CONNECTION,
+    CONTENT_LENGTH,
+    HOST,
+    TRANSFER_ENCODING,
+];
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/agent/mcp/{name}", any(proxy_root))
+        .route("/agent/mcp/{name}/{*path}", any(proxy))
+        .with_state(reqwest::Client::new())
+}
+
+async fn proxy_root(
+    State(client): State<reqwest::Client>,
+    Extension(state): Extension<Arc<AppState>>,
+    Path(name): Path<String>,
+    req: Request,
+) -> Response {
+    forward(&client, &state.ctx.mcp_servers, &name, "", req).await
+}
+
+async fn proxy(
+    State(client): State<reqwest::Client>,
+    Extension(state): Extension<Arc<AppState>>,
+    Path((name, path)): Path<(String, String)>,
+    req: Request,
+) -> Response {
+    forward(&client, &state.ctx.mcp_servers, &name, &path, req).await
+}
+
+/// URL of the proxy for the server with the given name, below the base URL of the host API.
+pub(super) fn proxy_url(base_url: &Url, name: &str) -> Url {
+    let mut url = base_url.clone();
+    // The name is a single, escaped segment even if it contains slashes.
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().extend(["agent", "mcp", name]);
+    }
+    url
+}
+
+/// Check that the names of the servers are unique and can be used as a segment of the proxy's
+/// URL without escaping.
+pub fn validate_servers(servers: &[McpServer]) -> anyhow::Result<()> {
+    let mut names = std::collections::HashSet::new();
+    for server in servers {
+        let name = server.name();
+        let valid = !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            anyhow::bail!(
+                "Invalid MCP server name {name:?}: use letters, digits, '-', '_' and '.' only"
+            );
+        }
+        if !names.insert(name) {
+            anyhow::bail!("Duplicate MCP server name {name:?}");
+        }
+    }
+    Ok(())
+}
+
+async fn forward(
+    client: &reqwest::Client,
+    servers: &[McpServer],
+    name: &str,
+    path: &str,
+    req: Request,
+) -> Response {
+    let Some((url, headers)) = servers.iter().find_map(|server| match server {
+        McpServer::Http {
+            name: server_name,
+            url,
+            headers,
+        } if server_name == name => Some((url, headers)),
+        _ => None,
+    }) else {
+        return (StatusCode::NOT_FOUND, format!("Unknown MCP server: {name}")).into_response();
+    };
+
+    let mut url = url.clone();
+    if !path.is_empty() {
+        let joined = format!("{}/{path}", url.path().trim_end_matches('/'));
+        url.set_path(&joined);
+    }
+    if let Some(query) = req.uri().query() {
+        url.set_query(Some(query));
+    }
+
+    let mut upstream_headers = forwarded_headers(req.headers());
+    for (name, value) in headers {
+        match (HeaderName::try_from(name.as_str()), value.parse()) {
+            (Ok(name), Ok(value)) => {
+                upstream_headers.insert(name, value);
+            }
+            _ => log::warn!("Ignoring invalid header {name} for MCP server"),
+        }
+    }
+
+    let method = req.method().clone();
+    let body = reqwest::Body::wrap_stream(req.into_body().into_data_stream());
+    let upstream = client
+        .request(method, url)
+        .headers(upstream_headers)
+        .body(body)
+        .send()
+        .await;
+
+    match upstream {
+        Ok(upstream) => {
+            let mut response = Response::builder().status(upstream.status());
+            if let Some(headers) = response.headers_mut() {
+                *headers = forwarded_headers(upstream.headers());
+            }
+            let body = Body::from_stream(upstream.bytes_stream().map_err(std::io::Error::other));
+            response
+                .body(body)
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+        Err(err) => {
+            log::error!("Failed to reach MCP server {name}: {err}");
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to reach MCP server {name}"),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut forwarded = headers.clone();
+    for header in &HOP_BY_HOP_HEADERS {
+        forwarded.remove(header);
+    }
+    forwarded
+}
@@ -0,0 +1,124 @@
+//! Tests for forwarding requests to MCP servers through the proxy.
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use agent_api::types::mcp::McpServer;
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::Router;
+
+use super::{forward, proxy_url, validate_servers};
+
+fn http_server(name: &str, url: &str) -> McpServer {
+    McpServer::Http {
+        name: name.to_owned(),
+        url: url.parse().unwrap(),
+        headers: BTreeMap::from([("X-Api-Key".to_owned(), "secret".to_owned())]),
+    }
+}
+
+/// An MCP server that echoes what it received.
+async fn echo_server() -> String {
+    async fn echo(req: Request) -> String {
+        let (parts, body) = req.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_owned())
+        };
+        format!(
+            "{} {} key={:?} auth={:?} body={}",
+            parts.method,
+            parts.uri,
+            header("x-api-key"),
+            header("authorization"),
+            String::from_utf8_lossy(&body)
+        )
+    }
+    let app = Router::new().fallback(echo);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn body_text(response: axum::response::Response) -> String {
+    String::from_utf8(
+        to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec(),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn requests_are_forwarded_with_the_configured_headers() {
+    let upstream = echo_server().await;
+    let servers = [http_server("tools", &format!("{upstream}/mcp/"))];
+    let request = Request::post("/api/agent/mcp/tools/messages?session=1")
+        .header("authorization", "Bearer agent-key")
+        .body(Body::from("{\"jsonrpc\":\"2.0\"}"))
+        .unwrap();
+
+    let response = forward(
+        &reqwest::Client::new(),
+        &servers,
+        "tools",
+        "messages",
+        request,
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        body_text(response).await,
+        "POST /mcp/messages?session=1 key=Some(\"secret\") auth=None body={\"jsonrpc\":\"2.0\"}"
+    );
+}
+
+#[tokio::test]
+async fn unknown_servers_are_not_found() {
+    let servers = [http_server("tools", "http://127.0.0.1:9/mcp")];
+    let request = Request::get("/").body(Body::empty()).unwrap();
+    let response = forward(&reqwest::Client::new(), &servers, "other", "", request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn proxy_urls_are_below_the_host_api() {
+    let base = "http://host.docker.internal:1234/api/".parse().unwrap();
+    assert_eq!(
+        proxy_url(&base, "tools").as_str(),
+        "http://host.docker.internal:1234/api/agent/mcp/tools"
+    );
+    // Names are validated when loaded, but are escaped regardless.
+    assert_eq!(
+        proxy_url(&base, "a/b?c#d").as_str(),
+        "http://host.docker.internal:1234/api/agent/mcp/a%2Fb%3Fc%23d"
+    );
+}
+
+#[test]
+fn server_names_are_single_url_segments() {
+    assert!(validate_servers(&[
+        http_server("tools", "http://localhost/"),
+        http_server("my_server-2.1", "http://localhost/"),
+    ])
+    .is_ok());
+    for name in ["", ".", "..", "a/b", "a?b", "a#b", "a b", "ü"] {
+        assert!(
+            validate_servers(&[http_server(name, "http://localhost/")]).is_err(),
+            "{name:?} should be rejected"
+        );
+    }
+    assert!(validate_servers(&[
+        http_server("tools", "http://localhost/"),
+        http_server("tools", "http://localhost/other"),
+    ])
+    .is_err());
+}
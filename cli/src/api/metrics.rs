@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::body::{Body, HttpBody as _};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use tracing::Instrument;
+
+use crate::api::AppState;
+
+/// Parts of the host API that are tracked separately.
+#[derive(Debug, Clone, Copy)]
+pub enum Component {
+    Llm,
+    Git,
+    Agent,
+    Mcp,
+}
+
+impl Component {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Component::Llm => "llm",
+            Component::Git => "git",
+            Component::Agent => "agent",
+            Component::Mcp => "mcp",
+        }
+    }
+}
+
+/// Prometheus metrics collected by the host API.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    tokens: IntCounterVec,
+    git_bytes: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new("minion_requests_total", "Number of handled API requests"),
+            &["component", "status"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "minion_request_duration_seconds",
+                "Latency of handled API requests",
+            )
+            .buckets(vec![
+                0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+            ]),
+            &["component"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "minion_llm_upstream_errors_total",
+                "Number of LLM requests that failed",
+            ),
+            &["status"],
+        )
+        .unwrap();
+        let tokens = IntCounterVec::new(
+            Opts::new("minion_llm_tokens_total", "Number of LLM tokens used"),
+            &["kind"],
+        )
+        .unwrap();
+        let git_bytes = IntCounterVec::new(
+            Opts::new(
+                "minion_git_bytes_total",
+                "Number of bytes transferred via git",
+            ),
+            &["direction"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry
+            .register(Box::new(request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(upstream_errors.clone()))
+            .unwrap();
+        registry.register(Box::new(tokens.clone())).unwrap();
+        registry.register(Box::new(git_bytes.clone())).unwrap();
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            upstream_errors,
+            tokens,
+            git_bytes,
+        }
+    }
+
+    /// Record token usage as reported in the `usage` object of an OpenAI-compatible response.
+    /// Supports both the chat completions and the responses API usage format.
+    pub fn record_token_usage(&self, usage: &serde_json::Value) {
+        let count = |keys: [&str; 2]| {
+            keys.iter()
+                .find_map(|key| usage.get(*key).and_then(|v| v.as_u64()))
+                .unwrap_or(0)
+        };
+        self.tokens
+            .with_label_values(&["prompt"])
+            .inc_by(count(["prompt_tokens", "input_tokens"]));
+        self.tokens
+            .with_label_values(&["completion"])
+            .inc_by(count(["completion_tokens", "output_tokens"]));
+    }
+
+    fn render(&self) -> Result<Vec<u8>, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// The metrics endpoint. Requires the agent API key, like the rest of the host API.
+pub fn router() -> Router {
+    Router::new().route("/metrics", get(metrics))
+}
+
+async fn metrics(Extension(state): Extension<Arc<AppState>>) -> Response {
+    match state.metrics.render() {
+        Ok(body) => ([(CONTENT_TYPE, TextEncoder::new().format_type())], body).into_response(),
+        Err(err) => {
+            log::error!("Failed to render metrics: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Middleware that records metrics and a tracing span for each request to a component.
+pub async fn track(component: Component, req: Request<Body>, next: Next) -> Response {
+    let Some(state) = req.extensions().get::<Arc<AppState>>().cloned() else {
+        return next.run(req).await;
+    };
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+    let span = tracing::info_span!(
+        parent: &state.run_span,
+        "request",
+        otel.name = %format!("{} {}", component.as_str(), path),
+        http.request.method = %method,
+        url.path = %path,
+        http.response.status_code = tracing::field::Empty,
+    );
+
+    // Push request bodies are buffered by the git proxy anyway, so we can measure them here.
+    let mut push_size = None;
+    let req = if matches!(component, Component::Git) && path.ends_with("/git-receive-pack") {
+        let (parts, body) = req.into_parts();
+        let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+        };
+        push_size = Some(bytes.len() as u64);
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        req
+    };
+
+    let start = Instant::now();
+    let response = next.run(req).instrument(span.clone()).await;
+    let elapsed = start.elapsed().as_secs_f64();
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+
+    let metrics = &state.metrics;
+    metrics
+        .requests
+        .with_label_values(&[component.as_str(), status.as_str()])
+        .inc();
+    metrics
+        .request_duration
+        .with_label_values(&[component.as_str()])
+        .observe(elapsed);
+
+    match component {
+        Component::Llm if !status.is_success() => {
+            metrics
+                .upstream_errors
+                .with_label_values(&[status.as_str()])
+                .inc();
+        }
+        Component::Git if status.is_success() && push_size.is_some() => {
+            metrics
+                .git_bytes
+                .with_label_values(&["push"])
+                .inc_by(push_size.unwrap_or_default());
+        }
+        Component::Git if status.is_success() && path.ends_with("/git-upload-pack") => {
+            // Fetch responses are fully buffered by the git proxy, so the size is known upfront.
+            if let Some(size) = response.body().size_hint().exact() {
+                metrics.git_bytes.with_label_values(&["fetch"]).inc_by(size);
+            }
+        }
+        _ => {}
+    }
+
+    response
+}
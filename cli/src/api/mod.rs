@@ -1,6 +1,9 @@
+use std::future::IntoFuture;
 use std::net::TcpListener;
 use std::sync::Arc;
 
+use agent_api::types::session::SessionState;
+use agent_api::types::task::TaskStatus;
 use axum::middleware;
 use axum::Extension;
 use axum::Router;
@@ -9,11 +12,25 @@ use tower_http::normalize_path::NormalizePathLayer;
 use tower_http::trace::TraceLayer;
 
 use crate::context::Context;
+use crate::events::{Event, LogStream};
+
+use inquiries::Inquiries;
+use metrics::{Component, Metrics};
+
+pub use dashboard::DashboardConfig;
+pub use inquiries::{InquiryMode, InquiryPolicy};
+pub use mcp::validate_servers as validate_mcp_servers;
 
 mod agent;
+pub mod artifacts;
 mod auth;
 mod chat;
+mod dashboard;
 mod git;
+mod inquiries;
+mod mcp;
+mod metrics;
+mod openapi;
 mod probes;
 
 #[derive(Debug, PartialEq)]
@@ -26,35 +43,144 @@ pub struct AppState {
     pub ctx: Arc<Context>,
     pub shutdown_tx: Mutex<Option<oneshot::Sender<TaskOutcome>>>,
     pub server_shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+    pub metrics: Arc<Metrics>,
+    pub inquiries: Arc<Inquiries>,
+    /// The task is queued until the agent fetches it for the first time.
+    pub task_status: std::sync::Mutex<TaskStatus>,
+    /// The claim of the agent that fetched the queued task, if it sent one.
+    pub task_claim: std::sync::Mutex<Option<String>>,
+    /// Messages of the user for the agent, in the order they were sent.
+    pub messages: std::sync::Mutex<Vec<String>>,
+    /// The agent's ACP session, saved so that it survives restarts of the agent.
+    pub session: std::sync::Mutex<Option<SessionState>>,
+    /// Held while an artifact is stored, so that uploads happen one at a time.
+    pub artifact_uploads: Mutex<()>,
+    /// Parent span for all requests handled by the server.
+    pub run_span: tracing::Span,
 }
 
-pub async fn run_server(listener: TcpListener, ctx: Context) -> anyhow::Result<TaskOutcome> {
+impl AppState {
+    /// Queue a message for the agent to pick up after its current turn.
+    pub fn send_message(&self, message: String) -> u64 {
+        let id = {
+            let mut messages = self.messages.lock().unwrap();
+            messages.push(message.clone());
+            messages.len() as u64
+        };
+        self.ctx.console.print(
+            LogStream::Stderr,
+            &format!("\n[message to agent] {message}\n"),
+        );
+        self.ctx.events.publish(Event::Message { id, message });
+        id
+    }
+
+    /// Ask the agent to stop. The agent notices the status change of its task, commits its work
+    /// and reports a failure. Does nothing if the task is already finished.
+    pub fn cancel_task(&self) {
+        {
+            let mut status = self.task_status.lock().unwrap();
+            if matches!(*status, TaskStatus::Completed | TaskStatus::Failed) {
+                return;
+            }
+            *status = TaskStatus::Cancelled;
+        }
+        self.ctx
+            .console
+            .print(LogStream::Stderr, "\n[cancel] asking the agent to stop\n");
+        self.ctx.events.publish(Event::TaskCancelled);
+    }
+}
+
+/// Serve the host API until the agent reports the outcome of its task.
+///
+/// Requests are traced as children of the span that is current when this function is called.
+/// The task is cancelled once `cancel_rx` receives a value.
+pub async fn run_server(
+    listener: TcpListener,
+    ctx: Context,
+    dashboard: Option<DashboardConfig>,
+    cancel_rx: oneshot::Receiver<()>,
+) -> anyhow::Result<TaskOutcome> {
     let ctx = Arc::new(ctx);
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<TaskOutcome>();
     let (server_shutdown_tx, server_shutdown_rx) = oneshot::channel::<()>();
 
+    let metrics = Arc::new(Metrics::new());
+    let inquiries = Arc::new(Inquiries::new(ctx.events.clone()));
     let state = Arc::new(AppState {
         ctx: ctx.clone(),
         shutdown_tx: Mutex::new(Some(shutdown_tx)),
         server_shutdown_tx: Mutex::new(Some(server_shutdown_tx)),
+        metrics: metrics.clone(),
+        inquiries: inquiries.clone(),
+        task_status: std::sync::Mutex::new(TaskStatus::Queued),
+        task_claim: std::sync::Mutex::new(None),
+        messages: std::sync::Mutex::new(Vec::new()),
+        session: std::sync::Mutex::new(None),
+        artifact_uploads: Mutex::new(()),
+        run_span: tracing::Span::current(),
     });
 
     let api_router = Router::new()
-        .merge(agent::router())
-        .merge(chat::router(ctx.clone()))
+        .merge(
+            agent::router().route_layer(middleware::from_fn(|req, next| {
+                metrics::track(Component::Agent, req, next)
+            })),
+        )
+        .merge(mcp::router().route_layer(middleware::from_fn(|req, next| {
+            metrics::track(Component::Mcp, req, next)
+        })))
+        .merge(
+            chat::router(ctx.clone(), metrics).route_layer(middleware::from_fn(|req, next| {
+                metrics::track(Component::Llm, req, next)
+            })),
+        )
         .route_layer(middleware::from_fn(auth::bearer_auth_middleware));
 
+    let git_router = git_proxy::scope("/api/agent/git", git::basic_auth_validator)
+        .route_layer(middleware::from_fn(git::push_events_middleware))
+        .route_layer(middleware::from_fn(|req, next| {
+            metrics::track(Component::Git, req, next)
+        }));
+
     let app = Router::new()
-        .merge(git_proxy::scope(
-            "/api/agent/git",
-            git::basic_auth_validator,
-        ))
+        .merge(git_router)
         .nest("/api", api_router)
         .merge(probes::router())
-        .layer(Extension(state))
+        .merge(openapi::router())
+        .merge(metrics::router().route_layer(middleware::from_fn(auth::bearer_auth_middleware)))
+        .layer(Extension(state.clone()))
         .layer(NormalizePathLayer::trim_trailing_slash())
         .layer(TraceLayer::new_for_http());
 
+    let canceller = tokio::spawn({
+        let state = state.clone();
+        async move {
+            if cancel_rx.await.is_ok() {
+                state.cancel_task();
+            }
+        }
+    });
+
+    let terminal_prompter = (ctx.inquiry_policy.mode == InquiryMode::Interactive).then(|| {
+        tokio::spawn(inquiries::prompt_on_terminal(
+            inquiries,
+            ctx.events.clone(),
+            ctx.console.clone(),
+        ))
+    });
+
+    let dashboard_server = match dashboard {
+        Some(DashboardConfig { listener, token }) => {
+            let app = dashboard::router(token).layer(Extension(state));
+            listener.set_nonblocking(true)?;
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            Some(tokio::spawn(axum::serve(listener, app).into_future()))
+        }
+        None => None,
+    };
+
     listener.set_nonblocking(true)?;
     let listener = tokio::net::TcpListener::from_std(listener)?;
 
@@ -62,10 +188,21 @@ pub async fn run_server(listener: TcpListener, ctx: Context) -> anyhow::Result<T
         let _ = server_shutdown_rx.await;
     });
 
-    tokio::select! {
+    let outcome = tokio::select! {
         res = server => res.map_err(|e| anyhow::anyhow!(e)).map(|()| TaskOutcome::Failure),
         outcome = shutdown_rx => outcome.map_err(|e| anyhow::anyhow!(e)),
+    };
+
+    canceller.abort();
+    if let Some(dashboard_server) = dashboard_server {
+        dashboard_server.abort();
     }
+    if let Some(terminal_prompter) = terminal_prompter {
+        terminal_prompter.abort();
+        ctx.console.resume();
+    }
+
+    outcome
 }
 
 pub async fn wait_until_ready(base_url: &str) -> Result<(), reqwest::Error> {
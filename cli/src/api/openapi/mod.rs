@@ -0,0 +1,68 @@
+//! OpenAPI document of the agent API, the contract for agents that are not written in Rust.
+//!
+//! A copy is kept in `libs/agent-api/openapi.json`, and a test ensures that it stays up to date.
+
+use agent_api::types::info::API_VERSION;
+use axum::routing::get;
+use axum::{Json, Router};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::OpenApi as OpenApiDocument;
+use utoipa::{Modify, OpenApi};
+
+use crate::api::{agent, artifacts};
+
+mod tests;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "minion agent API",
+        description = "The API of the minion runtime for agents working on a task.",
+        license(name = "MIT OR Apache-2.0")
+    ),
+    servers((url = "/api")),
+    paths(
+        agent::runtime_info,
+        agent::task_info,
+        agent::task_status,
+        agent::task_messages,
+        agent::task_progress,
+        agent::task_complete,
+        agent::task_fail,
+        agent::session,
+        agent::save_session,
+        agent::inquiry,
+        artifacts::upload_artifact,
+    ),
+    modifiers(&BearerAuth),
+    security(("api_key" = []))
+)]
+struct AgentApiDoc;
+
+/// Agents authenticate with the API key they get from the runtime.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+pub fn router() -> Router {
+    Router::new().route("/api/openapi.json", get(openapi))
+}
+
+/// The document is versioned like the API rather than the CLI.
+pub fn document() -> OpenApiDocument {
+    let mut document = AgentApiDoc::openapi();
+    document.info.version = API_VERSION.to_string();
+    document
+}
+
+async fn openapi() -> Json<OpenApiDocument> {
+    Json(document())
+}
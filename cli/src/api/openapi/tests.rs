@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use super::document;
+
+const COMMITTED: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../libs/agent-api/openapi.json"
+);
+
+/// Fails when the API types or routes change without updating the committed document.
+/// Run with `UPDATE_OPENAPI=1` to update it.
+#[test]
+fn committed_document_is_up_to_date() {
+    let generated = serde_json::to_value(document()).unwrap();
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        let json = serde_json::to_string_pretty(&generated).unwrap();
+        std::fs::write(COMMITTED, json + "\n").unwrap();
+        return;
+    }
+
+    let committed = std::fs::read_to_string(COMMITTED).unwrap_or_default();
+    let committed: serde_json::Value = serde_json::from_str(&committed).unwrap_or_default();
+    assert!(
+        generated == committed,
+        "{COMMITTED} is out of date. Run `UPDATE_OPENAPI=1 cargo test -p minion openapi` to update it."
+    );
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use agent_api::types::task::{Attachment, AttachmentContent};
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+mod tests;
+
+/// Attachments are sent to the agent as part of the task, so they are kept small.
+const MAX_ATTACHMENT_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Read a file to attach to the task. Text files are sent as they are, other files base64
+/// encoded.
+pub fn read(path: &Path) -> anyhow::Result<Attachment> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid attachment path: {}", path.display()))?
+        .to_owned();
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read attachment {}", path.display()))?
+        .len();
+    if size > MAX_ATTACHMENT_SIZE {
+        bail!(
+            "Attachment {} is larger than {MAX_ATTACHMENT_SIZE} bytes",
+            path.display()
+        );
+    }
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read attachment {}", path.display()))?;
+
+    let mime_type = mime_type(path);
+    let content = match String::from_utf8(bytes) {
+        Ok(text) if !mime_type.starts_with("image/") => AttachmentContent::Text { text },
+        Ok(text) => AttachmentContent::Base64 {
+            data: STANDARD.encode(text),
+        },
+        Err(err) => AttachmentContent::Base64 {
+            data: STANDARD.encode(err.into_bytes()),
+        },
+    };
+    let mime_type = match (&content, mime_type) {
+        (AttachmentContent::Text { .. }, "application/octet-stream") => "text/plain",
+        (_, mime_type) => mime_type,
+    };
+
+    Ok(Attachment {
+        name,
+        mime_type: mime_type.to_owned(),
+        content,
+    })
+}
+
+/// Guess the MIME type of a file from its extension.
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        Some("json") => "application/json",
+        Some("md") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("html" | "htm") => "text/html",
+        Some("txt" | "log") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,88 @@
+//! Tests for reading attachments.
+#![cfg(test)]
+
+use std::fs;
+use std::path::Path;
+
+use agent_api::types::task::AttachmentContent;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+use super::{mime_type, read, MAX_ATTACHMENT_SIZE};
+
+#[test]
+fn text_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(&path, "# Notes").unwrap();
+    let attachment = read(&path).unwrap();
+    assert_eq!(attachment.name, "notes.md");
+    assert_eq!(attachment.mime_type, "text/markdown");
+    assert!(matches!(attachment.content, AttachmentContent::Text { text } if text == "# Notes"));
+}
+
+#[test]
+fn text_without_known_extension_is_plain_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("Makefile");
+    fs::write(&path, "all:").unwrap();
+    let attachment = read(&path).unwrap();
+    assert_eq!(attachment.mime_type, "text/plain");
+    assert!(matches!(attachment.content, AttachmentContent::Text { .. }));
+}
+
+#[test]
+fn binary_file_is_base64_encoded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.bin");
+    let bytes = [0xff, 0xfe, 0x00, 0x01];
+    fs::write(&path, bytes).unwrap();
+    let attachment = read(&path).unwrap();
+    assert_eq!(attachment.mime_type, "application/octet-stream");
+    assert!(
+        matches!(attachment.content, AttachmentContent::Base64 { data } if data == STANDARD.encode(bytes))
+    );
+}
+
+#[test]
+fn image_is_base64_encoded_even_if_valid_utf8() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.PNG");
+    fs::write(&path, "not really a png").unwrap();
+    let attachment = read(&path).unwrap();
+    assert_eq!(attachment.mime_type, "image/png");
+    assert!(matches!(
+        attachment.content,
+        AttachmentContent::Base64 { .. }
+    ));
+}
+
+#[test]
+fn too_large_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.txt");
+    let file = fs::File::create(&path).unwrap();
+    file.set_len(MAX_ATTACHMENT_SIZE + 1).unwrap();
+    let err = read(&path).unwrap_err();
+    assert!(err.to_string().contains("larger than"), "{err}");
+}
+
+#[test]
+fn missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(read(&dir.path().join("missing.txt")).is_err());
+}
+
+#[test]
+fn mime_types() {
+    assert_eq!(mime_type(Path::new("a.jpeg")), "image/jpeg");
+    assert_eq!(mime_type(Path::new("a.JPG")), "image/jpeg");
+    assert_eq!(mime_type(Path::new("report.pdf")), "application/pdf");
+    assert_eq!(mime_type(Path::new("data.json")), "application/json");
+    assert_eq!(mime_type(Path::new("page.htm")), "text/html");
+    assert_eq!(mime_type(Path::new("build.log")), "text/plain");
+    assert_eq!(
+        mime_type(Path::new("archive.tar.gz")),
+        "application/octet-stream"
+    );
+    assert_eq!(mime_type(Path::new("README")), "application/octet-stream");
+}
@@ -0,0 +1,293 @@
+//! `minion batch`: run many independent tasks side by side.
+//!
+//! Each task runs like `minion run --no-merge`, with its own task branch, container, host API
+//! server and API key. The results are left on the task branches.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use agent_api::types::task::FileReference;
+use anyhow::{anyhow, bail, Context as _};
+use futures::StreamExt;
+use llm_proxy::ResponseCache;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::attachment;
+use crate::cli::report::{self, Outcome, RunReport};
+use crate::cli::run::{self, RunOptions};
+use crate::config::LLMRouterTable;
+
+mod tests;
+
+/// Number of tasks that run at the same time, unless configured otherwise.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A batch file, e.g.
+///
+/// ```toml
+/// concurrency = 2
+///
+/// [[tasks]]
+/// name = "lint-api"
+/// message = "Fix the clippy warnings in src/api"
+///
+/// [[tasks]]
+/// message = "Make the test `config::tests::defaults` pass"
+/// references = ["src/config.rs:40-80"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchFile {
+    /// Maximum number of tasks that run at the same time.
+    pub concurrency: Option<usize>,
+    pub tasks: Vec<BatchTask>,
+}
+
+/// Limit of tasks that run at the same time, from `--concurrency` or the batch file.
+pub fn concurrency(flag: Option<usize>, batch: &BatchFile) -> anyhow::Result<usize> {
+    let concurrency = flag.or(batch.concurrency).unwrap_or(DEFAULT_CONCURRENCY);
+    if concurrency == 0 {
+        bail!("The concurrency must be at least 1");
+    }
+    Ok(concurrency)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchTask {
+    /// Shown in the output and the summary. Defaults to `task-<number>`.
+    pub name: Option<String>,
+    pub message: String,
+    /// Overrides `--mode` for this task.
+    pub mode: Option<String>,
+    /// Overrides `--model` for this task.
+    pub model: Option<String>,
+    /// Files to attach, relative to the batch file.
+    #[serde(default)]
+    pub attach: Vec<PathBuf>,
+    /// Files or line ranges in the repository, e.g. `src/main.rs:10-20`.
+    #[serde(default)]
+    pub references: Vec<String>,
+    #[serde(default)]
+    pub acceptance_criteria: Vec<String>,
+}
+
+impl BatchFile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let batch: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if batch.tasks.is_empty() {
+            bail!("{} declares no tasks", path.display());
+        }
+        Ok(batch)
+    }
+}
+
+/// The result of one task of a batch.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub name: String,
+    #[serde(flatten)]
+    pub report: Option<RunReport>,
+    /// Why the task could not be run.
+    pub error: Option<String>,
+}
+
+/// A task with its inputs loaded, ready to run.
+pub struct PreparedTask {
+    name: String,
+    description: String,
+    options: RunOptions,
+}
+
+/// Load the inputs of all tasks, so that mistakes in the batch file surface before any task
+/// runs. `defaults` applies to all tasks, except where the batch file overrides it.
+pub fn prepare(
+    batch: BatchFile,
+    batch_path: &Path,
+    defaults: &RunOptions,
+) -> anyhow::Result<Vec<PreparedTask>> {
+    let base_dir = batch_path.parent().unwrap_or(Path::new("."));
+    batch
+        .tasks
+        .into_iter()
+        .enumerate()
+        .map(|(index, task)| {
+            let name = task.name.unwrap_or_else(|| format!("task-{}", index + 1));
+            let attachments = task
+                .attach
+                .iter()
+                .map(|path| attachment::read(&base_dir.join(path)))
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("Failed to load attachments of {name}"))?;
+            let references = task
+                .references
+                .iter()
+                .map(|reference| reference.parse::<FileReference>())
+                .collect::<Result<_, _>>()
+                .map_err(|err| anyhow!("Invalid reference in {name}: {err}"))?;
+            let options = RunOptions {
+                mode: task.mode.or_else(|| defaults.mode.clone()),
+                model: task.model.or_else(|| defaults.model.clone()),
+                attachments,
+                references,
+                acceptance_criteria: task.acceptance_criteria,
+                merge: false,
+                output_prefix: Some(format!("[{name}] ")),
+                ..defaults.clone()
+            };
+            Ok(PreparedTask {
+                name,
+                description: task.message,
+                options,
+            })
+        })
+        .collect()
+}
+
+/// Run the tasks of `batch`, at most `concurrency` at a time. Results are in the order of the
+/// batch file.
+///
+/// After Ctrl-C, the running tasks are cancelled and no further tasks are started.
+pub async fn run(
+    llm_router_table: LLMRouterTable,
+    llm_response_cache: Option<ResponseCache>,
+    containerfile: &Option<PathBuf>,
+    path: &PathBuf,
+    tasks: Vec<PreparedTask>,
+    concurrency: usize,
+) -> Vec<BatchResult> {
+    eprintln!("Running {} tasks, {concurrency} at a time.", tasks.len());
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let interrupts = tokio::spawn({
+        let interrupted = interrupted.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+
+    let results = futures::stream::iter(tasks)
+        .map(|task| {
+            let llm_router_table = llm_router_table.clone();
+            let llm_response_cache = llm_response_cache.clone();
+            let interrupted = interrupted.clone();
+            async move {
+                if interrupted.load(Ordering::SeqCst) {
+                    return BatchResult {
+                        name: task.name,
+                        report: None,
+                        error: Some("Not started, since the batch was interrupted".to_owned()),
+                    };
+                }
+                eprintln!("[{}] started", task.name);
+                let result = run::run(
+                    llm_router_table,
+                    llm_response_cache,
+                    containerfile,
+                    path,
+                    task.description,
+                    task.options,
+                )
+                .await;
+                match result {
+                    Ok(report) => {
+                        eprintln!("[{}] {}", task.name, outcome_name(report.outcome));
+                        BatchResult {
+                            name: task.name,
+                            report: Some(report),
+                            error: None,
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("[{}] error: {err:#}", task.name);
+                        BatchResult {
+                            name: task.name,
+                            report: None,
+                            error: Some(format!("{err:#}")),
+                        }
+                    }
+                }
+            }
+        })
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    interrupts.abort();
+    results
+}
+
+/// Failures to run a task take precedence over failed tasks.
+pub fn exit_code(results: &[BatchResult]) -> ExitCode {
+    let code = if results.iter().any(|result| result.report.is_none()) {
+        report::EXIT_INFRASTRUCTURE_ERROR
+    } else if results
+        .iter()
+        .filter_map(|result| result.report.as_ref())
+        .any(|report| report.outcome != Outcome::Completed)
+    {
+        report::EXIT_FAILED
+    } else {
+        report::EXIT_COMPLETED
+    };
+    ExitCode::from(code)
+}
+
+/// Render a table with a row per task.
+pub fn summary_table(results: &[BatchResult]) -> String {
+    let header = ["TASK", "OUTCOME", "FILES", "TOKENS", "BRANCH"].map(str::to_owned);
+    let rows: Vec<[String; 5]> = results
+        .iter()
+        .map(|result| match &result.report {
+            Some(report) => [
+                result.name.clone(),
+                outcome_name(report.outcome).to_owned(),
+                report.changed_files.len().to_string(),
+                (report.usage.prompt_tokens + report.usage.completion_tokens).to_string(),
+                report.fork_branch.clone(),
+            ],
+            None => [
+                result.name.clone(),
+                "error".to_owned(),
+                "-".to_owned(),
+                "-".to_owned(),
+                result.error.clone().unwrap_or_default(),
+            ],
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Completed => "completed",
+        Outcome::Failed => "failed",
+        Outcome::Cancelled => "cancelled",
+    }
+}
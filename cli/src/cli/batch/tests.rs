@@ -0,0 +1,117 @@
+#![cfg(test)]
+
+use std::path::Path;
+
+use agent_api::types::task::{FileReference, LineRange};
+
+use super::{prepare, summary_table, BatchFile, BatchResult};
+use crate::api::InquiryPolicy;
+use crate::cli::report::{Outcome, RunReport, Usage};
+use crate::cli::run::RunOptions;
+
+fn defaults() -> RunOptions {
+    RunOptions {
+        dashboard: false,
+        inquiry_policy: InquiryPolicy::default(),
+        permission_policy: None,
+        mcp_servers: Vec::new(),
+        mode: Some("code".to_owned()),
+        model: None,
+        attachments: Vec::new(),
+        references: Vec::new(),
+        acceptance_criteria: Vec::new(),
+        merge: true,
+        json: false,
+        output_prefix: None,
+    }
+}
+
+#[test]
+fn tasks_inherit_defaults_and_are_never_merged() {
+    let batch: BatchFile = toml::from_str(
+        r#"
+        [[tasks]]
+        message = "Fix the lints"
+
+        [[tasks]]
+        name = "config"
+        message = "Fix the config test"
+        mode = "plan"
+        references = ["src/config.rs:40-80"]
+        "#,
+    )
+    .unwrap();
+
+    let tasks = prepare(batch, Path::new("tasks.toml"), &defaults()).unwrap();
+    assert_eq!(tasks.len(), 2);
+
+    assert_eq!(tasks[0].name, "task-1");
+    assert_eq!(tasks[0].options.mode.as_deref(), Some("code"));
+    assert!(!tasks[0].options.merge);
+
+    assert_eq!(tasks[1].name, "config");
+    assert_eq!(tasks[1].description, "Fix the config test");
+    assert_eq!(tasks[1].options.mode.as_deref(), Some("plan"));
+    assert_eq!(tasks[1].options.output_prefix.as_deref(), Some("[config] "));
+    assert_eq!(
+        tasks[1].options.references,
+        vec![FileReference {
+            path: "src/config.rs".to_owned(),
+            lines: Some(LineRange { start: 40, end: 80 }),
+        }]
+    );
+}
+
+#[test]
+fn invalid_references_are_rejected_before_running() {
+    let batch: BatchFile = toml::from_str(
+        r#"
+        [[tasks]]
+        message = "Fix it"
+        references = ["src/main.rs:20-10"]
+        "#,
+    )
+    .unwrap();
+
+    let err = prepare(batch, Path::new("tasks.toml"), &defaults())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Invalid reference in task-1"));
+}
+
+#[test]
+fn summary_has_a_row_per_task() {
+    let results = vec![
+        BatchResult {
+            name: "lints".to_owned(),
+            report: Some(RunReport {
+                task_id: "0190".to_owned(),
+                fork_branch: "0190".to_owned(),
+                outcome: Outcome::Completed,
+                description: Some("Fixed".to_owned()),
+                merged: false,
+                merge_error: None,
+                changed_files: vec!["src/lib.rs".to_owned()],
+                artifacts: Vec::new(),
+                usage: Usage {
+                    llm_calls: 2,
+                    prompt_tokens: 100,
+                    completion_tokens: 20,
+                },
+            }),
+            error: None,
+        },
+        BatchResult {
+            name: "config".to_owned(),
+            report: None,
+            error: Some("Docker is not running".to_owned()),
+        },
+    ];
+
+    assert_eq!(
+        summary_table(&results),
+        "TASK    OUTCOME    FILES  TOKENS  BRANCH\n\
+         lints   completed  1      120     0190\n\
+         config  error      -      -       Docker is not running\n"
+    );
+}
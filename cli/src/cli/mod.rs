@@ -1,22 +1,242 @@
-use std::path::PathBuf;
+use std::io::{IsTerminal as _, Read as _};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
+use agent_api::types::mcp::{McpServer, McpServers};
+use agent_api::types::permission::PermissionPolicy;
+use agent_api::types::task::{Attachment, FileReference};
+use anyhow::{anyhow, bail, Context as _};
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use llm_proxy::ResponseCache;
 
-use crate::config::{Config, LLMProvider};
+use crate::api::{validate_mcp_servers, InquiryMode, InquiryPolicy};
+use crate::config::{Config, LLMProvider, LLMRouterTable};
 use crate::providers::{chatgpt, gemini, openrouter};
+use crate::telemetry::Telemetry;
 
-mod editor;
+mod attachment;
+mod batch;
+pub mod editor;
+pub mod report;
 mod run;
+mod tests;
 
-#[derive(Args, Debug, Clone, Default)]
+/// How long to wait for leftover tasks when a run is over.
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
 struct RunArgs {
-    /// Task description
+    /// Task description, or `-` to read it from stdin
     #[arg(short = 'm', long, global = true, help_heading = "Run Options")]
     message: Option<String>,
 
+    /// Read the task description from a file
+    #[arg(
+        long,
+        value_name = "FILE",
+        global = true,
+        conflicts_with = "message",
+        help_heading = "Run Options"
+    )]
+    message_file: Option<PathBuf>,
+
+    /// Never prompt: fail if no task description is given, auto-answer inquiries and reject
+    /// permission requests that the permission policy leaves to the user
+    #[arg(short = 'y', long, global = true, help_heading = "Run Options")]
+    yes: bool,
+
+    /// Leave the changes on the task branch instead of merging them into the current branch
+    #[arg(long, global = true, help_heading = "Run Options")]
+    no_merge: bool,
+
+    /// Print a JSON report of the run to stdout, and everything else to stderr
+    #[arg(long, global = true, help_heading = "Run Options")]
+    json: bool,
+
     /// Use the Containerfile located at the specified path
     #[arg(long, global = true, help_heading = "Run Options")]
     containerfile: Option<PathBuf>,
+
+    /// Bypass the LLM response cache for this run
+    #[arg(long, global = true, help_heading = "Run Options")]
+    no_cache: bool,
+
+    /// Serve a web dashboard on localhost to monitor the task and answer inquiries
+    #[arg(long, global = true, help_heading = "Run Options")]
+    dashboard: bool,
+
+    /// How to handle questions of the agent [default: interactive, or auto-answer if stdin is not
+    /// a terminal or the task description is read from it]
+    #[arg(long, value_enum, global = true, help_heading = "Run Options")]
+    inquiry_mode: Option<InquiryMode>,
+
+    /// Reply with the default answer if a question is not answered within this many seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        global = true,
+        help_heading = "Run Options"
+    )]
+    inquiry_timeout: Option<u64>,
+
+    /// The answer to use for questions that are not answered by the user
+    #[arg(
+        long,
+        value_name = "ANSWER",
+        global = true,
+        help_heading = "Run Options"
+    )]
+    inquiry_default_answer: Option<String>,
+
+    /// TOML file with the policy for answering permission requests of the agent
+    ///
+    /// Requests that the policy leaves to the user are rejected if inquiries are not answered
+    /// interactively, e.g. with `--yes` and in batch runs. Without a policy file, all permission
+    /// requests are then rejected.
+    #[arg(long, value_name = "FILE", global = true, help_heading = "Run Options")]
+    permission_policy: Option<PathBuf>,
+
+    /// TOML file declaring MCP servers (`[[mcp_servers]]`) to make available to the agent
+    #[arg(long, value_name = "FILE", global = true, help_heading = "Run Options")]
+    mcp_servers: Option<PathBuf>,
+
+    /// Session mode of the agent, e.g. `plan` for a run that makes no changes
+    #[arg(long, global = true, help_heading = "Run Options")]
+    mode: Option<String>,
+
+    /// Model for the agent to use, if it offers a choice
+    #[arg(long, global = true, help_heading = "Run Options")]
+    model: Option<String>,
+
+    /// Share a file that is not in the repository with the agent, e.g. a log or a screenshot
+    #[arg(long, value_name = "FILE", global = true, help_heading = "Run Options")]
+    attach: Vec<PathBuf>,
+
+    /// Point the agent to a file in the repository, optionally to lines of it (`path:start-end`)
+    #[arg(
+        long = "ref",
+        value_name = "PATH[:LINES]",
+        global = true,
+        help_heading = "Run Options"
+    )]
+    references: Vec<FileReference>,
+
+    /// A condition the result must meet for the task to be done
+    #[arg(
+        long = "accept",
+        value_name = "CRITERION",
+        global = true,
+        help_heading = "Run Options"
+    )]
+    acceptance_criteria: Vec<String>,
+}
+
+impl RunArgs {
+    /// The inquiry policy of a single run. Inquiries are only answered interactively if they can
+    /// be answered on the terminal, i.e. stdin is a terminal and the task description is not read
+    /// from it, or on the dashboard.
+    fn inquiry_policy(&self, stdin_is_terminal: bool) -> anyhow::Result<InquiryPolicy> {
+        let answerable =
+            (stdin_is_terminal && self.message.as_deref() != Some("-")) || self.dashboard;
+        let mode = match (self.inquiry_mode, self.yes) {
+            (Some(InquiryMode::Interactive), true) => {
+                bail!("--yes cannot be combined with --inquiry-mode interactive")
+            }
+            (Some(InquiryMode::Interactive), false) if !answerable => bail!(
+                "Inquiries cannot be answered on the terminal if stdin is not a terminal or the \
+                task description is read from it. Use --dashboard or another --inquiry-mode."
+            ),
+            (Some(mode), _) => mode,
+            (None, false) if answerable => InquiryMode::Interactive,
+            (None, _) => InquiryMode::AutoAnswer,
+        };
+        Ok(self.inquiry_policy_with_mode(mode))
+    }
+
+    /// The inquiry policy of the tasks of a batch. Nobody could tell which task is asking, so
+    /// tasks never ask on the terminal.
+    fn batch_inquiry_policy(&self) -> anyhow::Result<InquiryPolicy> {
+        let mode = match self.inquiry_mode {
+            Some(InquiryMode::Interactive) => bail!("Batch runs cannot be interactive."),
+            Some(mode) => mode,
+            None => InquiryMode::AutoAnswer,
+        };
+        Ok(self.inquiry_policy_with_mode(mode))
+    }
+
+    fn inquiry_policy_with_mode(&self, mode: InquiryMode) -> InquiryPolicy {
+        let default = InquiryPolicy::default();
+        InquiryPolicy {
+            mode,
+            timeout: self.inquiry_timeout.map(Duration::from_secs),
+            default_answer: self
+                .inquiry_default_answer
+                .clone()
+                .unwrap_or(default.default_answer),
+        }
+    }
+
+    /// The permission policy from `--permission-policy`. If inquiries are not answered
+    /// interactively, nobody could answer a permission request, so these are rejected instead.
+    fn permission_policy(
+        &self,
+        inquiry_mode: InquiryMode,
+    ) -> anyhow::Result<Option<PermissionPolicy>> {
+        let policy = match &self.permission_policy {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)?;
+                Some(toml::from_str::<PermissionPolicy>(&content)?)
+            }
+            None => None,
+        };
+        if inquiry_mode == InquiryMode::Interactive {
+            return Ok(policy);
+        }
+        Ok(Some(policy.unwrap_or_default().without_asking()))
+    }
+
+    fn mcp_servers(&self) -> anyhow::Result<Vec<McpServer>> {
+        let Some(path) = &self.mcp_servers else {
+            return Ok(Vec::new());
+        };
+        let content = std::fs::read_to_string(path)?;
+        let servers: McpServers = toml::from_str(&content)?;
+        validate_mcp_servers(&servers.mcp_servers)?;
+        Ok(servers.mcp_servers)
+    }
+
+    fn attachments(&self) -> anyhow::Result<Vec<Attachment>> {
+        self.attach
+            .iter()
+            .map(|path| attachment::read(path))
+            .collect()
+    }
+
+    /// The task description from `-m`, `--message-file` or, unless `--yes` is set, the editor.
+    fn task_description(&self) -> anyhow::Result<String> {
+        let description = match (&self.message, &self.message_file) {
+            (Some(message), _) if message == "-" => {
+                let mut message = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut message)
+                    .context("Failed to read the task description from stdin")?;
+                message
+            }
+            (Some(message), _) => message.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+            (None, None) if self.yes => {
+                bail!("No task description given. Use -m or --message-file with --yes.")
+            }
+            (None, None) => read_task_from_editor()?,
+        };
+        let description = description.trim();
+        if description.is_empty() {
+            bail!("The task description is empty.");
+        }
+        Ok(description.to_owned())
+    }
 }
 
 #[derive(Subcommand)]
@@ -25,6 +245,20 @@ enum Command {
     #[command(name = "run", alias = "")]
     Run,
 
+    /// Run the tasks of a TOML file side by side, leaving the results on their task branches
+    ///
+    /// Run options apply to all tasks. The file declares the tasks as `[[tasks]]` with a
+    /// `message` and optionally a `name`, `mode`, `model`, files to `attach`, `references` and
+    /// `acceptance_criteria`.
+    Batch {
+        /// The batch file
+        file: PathBuf,
+
+        /// Maximum number of tasks that run at the same time [default: 4]
+        #[arg(long)]
+        concurrency: Option<usize>,
+    },
+
     /// Login using one of the supported LLM providers
     Login {
         #[arg(value_enum)]
@@ -33,7 +267,14 @@ enum Command {
 }
 
 #[derive(Parser)]
-#[command(version, author, about, long_about = None)]
+#[command(
+    version,
+    author,
+    about,
+    long_about = None,
+    after_help = "Exit codes of `minion run` and `minion batch`: 0 if all tasks were completed, 1 if \
+        any failed or was cancelled, 3 if the changes could not be merged, 4 if any could not be run."
+)]
 struct Cli {
     /// Enable trace logging
     #[arg(long)]
@@ -43,6 +284,10 @@ struct Cli {
     #[arg(long)]
     debug: bool,
 
+    /// Export traces via OTLP, configured by the OTEL_EXPORTER_OTLP_* environment variables
+    #[arg(long)]
+    otlp: bool,
+
     #[command(flatten)]
     run: RunArgs,
 
@@ -51,14 +296,28 @@ struct Cli {
 }
 
 impl Cli {
-    fn invalid_use_of_run_args(&self) -> bool {
-        let is_run_command = matches!(self.command, Some(Command::Run)) || self.command.is_none();
-
-        !is_run_command && (self.run.message.is_some() || self.run.containerfile.is_some())
+    /// Why the given run options are not valid with the command, if they are not.
+    fn invalid_use_of_run_args(&self) -> Option<&'static str> {
+        match self.command {
+            None | Some(Command::Run) => None,
+            Some(Command::Batch { .. }) => {
+                let run = &self.run;
+                let task_args = run.message.is_some()
+                    || run.message_file.is_some()
+                    || !run.attach.is_empty()
+                    || !run.references.is_empty()
+                    || !run.acceptance_criteria.is_empty();
+                task_args.then_some("Task descriptions and inputs are set in the batch file.")
+            }
+            Some(_) if self.run != RunArgs::default() => {
+                Some("Run options are only valid with `minion`, `minion run` or `minion batch`.")
+            }
+            Some(_) => None,
+        }
     }
 }
 
-pub fn exec() {
+pub fn exec() -> ExitCode {
     let cli = Cli::parse();
     let mut builder = env_logger::Builder::from_default_env();
     builder
@@ -76,110 +335,194 @@ pub fn exec() {
 
     builder.init();
 
-    if cli.invalid_use_of_run_args() {
-        eprintln!("Run options are only valid with `minion` or `minion run`.");
-        std::process::exit(2);
+    if let Some(reason) = cli.invalid_use_of_run_args() {
+        eprintln!("{reason}");
+        return ExitCode::from(2);
     }
 
-    match cli.command.unwrap_or(Command::Run) {
-        Command::Run => {
-            let mut config = Config::load_or_create().expect("Failed to load config");
-
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create runtime")
-                .block_on(async {
-                    chatgpt::refresh_if_needed(&mut config)
-                        .await
-                        .expect("Failed to refresh ChatGPT login");
-                });
-
-            let Some(llm_router_table) = config.llm_router_table() else {
-                eprintln!("You currently don't have a LLM API key configured.");
-                eprintln!("Run `minion login` to authenticate with a supported provider.");
-                eprintln!(
-                    "Supported providers: {}",
-                    LLMProvider::value_variants()
-                        .iter()
-                        .map(|p| p.to_string())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                );
-                eprintln!("If your LLM provider is not listed, please contribute!");
-                std::process::exit(1);
-            };
-
-            let task_description = if let Some(msg) = cli.run.message {
-                msg
-            } else {
-                read_task_from_editor()
-            };
-
-            println!("{task_description}");
-            println!();
-
-            println!("Working on the task.");
-
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create runtime")
-                .block_on(async {
-                    run::run(
-                        llm_router_table,
-                        &cli.run.containerfile,
-                        &std::env::current_dir().expect("Failed to get current dir"),
-                        task_description,
-                    )
-                    .await
-                    .expect("Failed to run task");
-                });
-        }
-        Command::Login {
-            llm_provider: provider,
-        } => {
-            tokio::runtime::Runtime::new()
-                .expect("Failed to create runtime")
-                .block_on(async {
-                    let config = Config::load_or_create().expect("Failed to load config");
-                    match provider {
-                        LLMProvider::ChatGpt => chatgpt::login_flow(config)
-                            .await
-                            .expect("Failed to start login flow"),
-                        LLMProvider::OpenRouter => openrouter::login_flow(config)
-                            .await
-                            .expect("Failed to start login flow"),
-                        LLMProvider::GoogleGemini => gemini::login_flow(config)
-                            .await
-                            .expect("Failed to start login flow"),
-                    }
-                });
-        }
+    let json = cli.run.json;
+    let telemetry = match cli.otlp.then(Telemetry::init).transpose() {
+        Ok(telemetry) => telemetry,
+        Err(err) => return error_exit(err.context("Failed to initialize telemetry"), json),
+    };
+
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run_task(cli.run),
+        Command::Batch { file, concurrency } => run_batch(cli.run, &file, concurrency),
+        Command::Login { llm_provider } => login(llm_provider).map(|()| ExitCode::SUCCESS),
+    };
+
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
     }
+
+    result.unwrap_or_else(|err| error_exit(err, json))
 }
 
-fn read_task_from_editor() -> String {
-    let initial_message =
-        "\n\n# Please describe your task. Lines starting with '#' will be ignored.";
-    let edited = editor::Editor::new()
-        .edit(initial_message)
-        .unwrap_or_else(|err| {
-            eprintln!("Failed to open editor: {err}");
-            std::process::exit(1);
-        });
-
-    let edited = edited
-        .map(|text| {
-            text.lines()
-                .filter(|line| !line.trim_start().starts_with('#'))
+/// Report an error that kept the command from running, as JSON if requested.
+fn error_exit(err: anyhow::Error, json: bool) -> ExitCode {
+    if json {
+        println!("{}", serde_json::json!({ "error": format!("{err:#}") }));
+    } else {
+        eprintln!("Error: {err:#}");
+    }
+    ExitCode::from(report::EXIT_INFRASTRUCTURE_ERROR)
+}
+
+/// The configured LLM providers and, unless disabled, the response cache.
+fn llm_config(
+    runtime: &tokio::runtime::Runtime,
+    no_cache: bool,
+) -> anyhow::Result<(LLMRouterTable, Option<ResponseCache>)> {
+    let mut config = Config::load_or_create().context("Failed to load config")?;
+
+    runtime
+        .block_on(chatgpt::refresh_if_needed(&mut config))
+        .context("Failed to refresh ChatGPT login")?;
+
+    let Some(llm_router_table) = config.llm_router_table() else {
+        eprintln!("Run `minion login` to authenticate with a supported provider.");
+        eprintln!(
+            "Supported providers: {}",
+            LLMProvider::value_variants()
+                .iter()
+                .map(|p| p.to_string())
                 .collect::<Vec<_>>()
-                .join("\n")
-        })
-        .expect("Failed to read from editor");
+                .join(", ")
+        );
+        eprintln!("If your LLM provider is not listed, please contribute!");
+        bail!("You currently don't have a LLM API key configured.");
+    };
+
+    let llm_response_cache = if no_cache {
+        None
+    } else {
+        config
+            .llm_response_cache()
+            .context("Failed to locate LLM response cache")?
+    };
+    Ok((llm_router_table, llm_response_cache))
+}
+
+fn run_task(args: RunArgs) -> anyhow::Result<ExitCode> {
+    let runtime = tokio::runtime::Runtime::new().context("Failed to create runtime")?;
+    let (llm_router_table, llm_response_cache) = llm_config(&runtime, args.no_cache)?;
+
+    let inquiry_policy = args.inquiry_policy(std::io::stdin().is_terminal())?;
+    let permission_policy = args
+        .permission_policy(inquiry_policy.mode)
+        .context("Failed to load permission policy")?;
+    let mcp_servers = args.mcp_servers().context("Failed to load MCP servers")?;
+    let attachments = args.attachments().context("Failed to load attachments")?;
+    let task_description = args.task_description()?;
+
+    eprintln!("{task_description}");
+    eprintln!();
+
+    eprintln!("Working on the task.");
+
+    let current_dir = std::env::current_dir().context("Failed to get current dir")?;
+    let report = runtime.block_on(run::run(
+        llm_router_table,
+        llm_response_cache,
+        &args.containerfile,
+        &current_dir,
+        task_description,
+        run::RunOptions {
+            dashboard: args.dashboard,
+            inquiry_policy,
+            permission_policy,
+            mcp_servers,
+            mode: args.mode,
+            model: args.model,
+            attachments,
+            references: args.references,
+            acceptance_criteria: args.acceptance_criteria,
+            merge: !args.no_merge,
+            json: args.json,
+            output_prefix: None,
+        },
+    ));
+    // Don't wait for blocking tasks that outlive the run, such as an editor opened to answer an
+    // inquiry that has since been answered elsewhere.
+    runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
+    let report = report.context("Failed to run task")?;
+
+    if args.json {
+        println!("{}", serde_json::to_string(&report)?);
+    }
+    Ok(report.exit_code())
+}
+
+fn run_batch(args: RunArgs, file: &Path, concurrency: Option<usize>) -> anyhow::Result<ExitCode> {
+    let batch = batch::BatchFile::load(file)?;
+    let concurrency = batch::concurrency(concurrency, &batch)?;
+
+    let runtime = tokio::runtime::Runtime::new().context("Failed to create runtime")?;
+    let (llm_router_table, llm_response_cache) = llm_config(&runtime, args.no_cache)?;
 
-    let trimmed = edited.trim();
+    let inquiry_policy = args.batch_inquiry_policy()?;
+    let defaults = run::RunOptions {
+        dashboard: args.dashboard,
+        permission_policy: args
+            .permission_policy(inquiry_policy.mode)
+            .context("Failed to load permission policy")?,
+        inquiry_policy,
+        mcp_servers: args.mcp_servers().context("Failed to load MCP servers")?,
+        mode: args.mode.clone(),
+        model: args.model.clone(),
+        attachments: Vec::new(),
+        references: Vec::new(),
+        acceptance_criteria: Vec::new(),
+        merge: false,
+        json: args.json,
+        output_prefix: None,
+    };
+    let tasks = batch::prepare(batch, file, &defaults)?;
 
-    if trimmed.is_empty() {
-        eprintln!("No input received.");
-        std::process::exit(1);
+    let results = runtime.block_on(batch::run(
+        llm_router_table,
+        llm_response_cache,
+        &args.containerfile,
+        &std::env::current_dir().context("Failed to get current dir")?,
+        tasks,
+        concurrency,
+    ));
+
+    if args.json {
+        println!("{}", serde_json::to_string(&results)?);
+    } else {
+        println!();
+        print!("{}", batch::summary_table(&results));
     }
+    Ok(batch::exit_code(&results))
+}
+
+fn login(provider: LLMProvider) -> anyhow::Result<()> {
+    let config = Config::load_or_create().context("Failed to load config")?;
+    tokio::runtime::Runtime::new()
+        .context("Failed to create runtime")?
+        .block_on(async {
+            match provider {
+                LLMProvider::ChatGpt => chatgpt::login_flow(config).await,
+                LLMProvider::OpenRouter => openrouter::login_flow(config).await,
+                LLMProvider::GoogleGemini => gemini::login_flow(config).await,
+            }
+        })
+        .context("Failed to start login flow")
+}
+
+fn read_task_from_editor() -> anyhow::Result<String> {
+    let initial_message =
+        "\n\n# Please describe your task. Lines starting with '#' will be ignored.";
+    let edited = editor::Editor::new()
+        .edit(initial_message)
+        .map_err(|err| anyhow!("Failed to open editor: {err}"))?
+        .ok_or_else(|| anyhow!("Failed to read from editor"))?;
 
-    trimmed.to_owned()
+    Ok(edited
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n"))
 }
@@ -0,0 +1,128 @@
+//! Machine-readable summary of a run, printed with `minion run --json`.
+
+use std::process::ExitCode;
+
+use agent_api::types::artifact::Artifact;
+use serde::Serialize;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{broadcast, oneshot};
+
+use crate::events::Event;
+
+/// The agent completed the task and its changes were merged, or left on the task branch with
+/// `--no-merge`.
+pub const EXIT_COMPLETED: u8 = 0;
+/// The agent failed, or the task was cancelled.
+pub const EXIT_FAILED: u8 = 1;
+/// The agent completed the task, but its changes could not be merged.
+pub const EXIT_MERGE_CONFLICT: u8 = 3;
+/// The task could not be run, e.g. because Docker is not available.
+pub const EXIT_INFRASTRUCTURE_ERROR: u8 = 4;
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub task_id: String,
+    /// The branch with the agent's work.
+    pub fork_branch: String,
+    pub outcome: Outcome,
+    /// What the agent reported when it completed or failed the task.
+    pub description: Option<String>,
+    /// Whether the changes were squash-merged into the current branch.
+    pub merged: bool,
+    /// Why the changes could not be merged.
+    pub merge_error: Option<String>,
+    /// Paths changed on the task branch, relative to the repository.
+    pub changed_files: Vec<String>,
+    pub artifacts: Vec<Artifact>,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// LLM usage of the agent. Token counts only include responses that reported them.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Usage {
+    pub llm_calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl RunReport {
+    pub fn exit_code(&self) -> ExitCode {
+        let code = match (self.outcome, &self.merge_error) {
+            (Outcome::Completed, None) => EXIT_COMPLETED,
+            (Outcome::Completed, Some(_)) => EXIT_MERGE_CONFLICT,
+            (Outcome::Failed | Outcome::Cancelled, _) => EXIT_FAILED,
+        };
+        ExitCode::from(code)
+    }
+}
+
+/// What the events of a run tell about its outcome.
+#[derive(Debug, Default)]
+pub struct EventSummary {
+    pub description: Option<String>,
+    pub cancelled: bool,
+    pub usage: Usage,
+}
+
+impl EventSummary {
+    fn record(&mut self, event: Event) {
+        match event {
+            Event::LlmCall {
+                prompt_tokens,
+                completion_tokens,
+                ..
+            } => {
+                self.usage.llm_calls += 1;
+                self.usage.prompt_tokens += prompt_tokens.unwrap_or(0);
+                self.usage.completion_tokens += completion_tokens.unwrap_or(0);
+            }
+            Event::LlmUsage {
+                prompt_tokens,
+                completion_tokens,
+            } => {
+                self.usage.prompt_tokens += prompt_tokens;
+                self.usage.completion_tokens += completion_tokens;
+            }
+            Event::TaskCancelled => self.cancelled = true,
+            Event::TaskCompleted { description } | Event::TaskFailed { description } => {
+                self.description = Some(description);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Summarize events until `stop_rx` receives a value, including events that were published
+/// before that but not yet received.
+pub async fn summarize_events(
+    mut rx: broadcast::Receiver<Event>,
+    mut stop_rx: oneshot::Receiver<()>,
+) -> EventSummary {
+    let mut summary = EventSummary::default();
+    loop {
+        tokio::select! {
+            biased;
+            event = rx.recv() => match event {
+                Ok(event) => summary.record(event),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return summary,
+            },
+            _ = &mut stop_rx => break,
+        }
+    }
+    loop {
+        match rx.try_recv() {
+            Ok(event) => summary.record(event),
+            Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => return summary,
+        }
+    }
+}
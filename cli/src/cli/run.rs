@@ -1,23 +1,62 @@
+use agent_api::types::artifact::Artifact;
+use agent_api::types::mcp::McpServer;
+use agent_api::types::permission::PermissionPolicy;
+use agent_api::types::task::{Attachment, FileReference};
 use anyhow::anyhow;
+use llm_proxy::ResponseCache;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use tracing::Instrument;
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
-    api::TaskOutcome,
-    config::LLMRouterTable,
+    api::{artifacts, DashboardConfig, InquiryPolicy, TaskOutcome},
+    cli::report::{self, Outcome, RunReport},
+    config::{Config, LLMRouterTable},
+    console::Console,
     context::{self, Context},
+    events::EventHub,
     runtime::ContainerConfig,
 };
 
 const AGENT_CONTAINER_IMAGE: &str = "ghcr.io/minionrt/minionrt:codex-x86-64-latest";
 
+/// How long the agent has to stop after the user cancelled the task, before it is killed.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the agent is supervised during a run.
+#[derive(Clone)]
+pub struct RunOptions {
+    /// Serve the web dashboard.
+    pub dashboard: bool,
+    pub inquiry_policy: InquiryPolicy,
+    pub permission_policy: Option<PermissionPolicy>,
+    pub mcp_servers: Vec<McpServer>,
+    /// Session mode for the agent, e.g. `plan` for a run that makes no changes.
+    pub mode: Option<String>,
+    pub model: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub references: Vec<FileReference>,
+    pub acceptance_criteria: Vec<String>,
+    /// Squash-merge the changes into the current branch once the task is completed.
+    pub merge: bool,
+    /// Keep stdout free for a machine-readable report, by writing all output to stderr.
+    pub json: bool,
+    /// Written before each line of the agent's output, e.g. the name of the task in a batch.
+    pub output_prefix: Option<String>,
+}
+
 pub async fn run<P: AsRef<Path>>(
     llm_router_table: LLMRouterTable,
+    llm_response_cache: Option<ResponseCache>,
     containerfile: &Option<P>,
     path: &P,
     task_description: String,
-) -> anyhow::Result<()> {
+    options: RunOptions,
+) -> anyhow::Result<RunReport> {
     let rt = crate::runtime::LocalDockerRuntime::connect()?;
     let agent_api_host = rt.bridge_network_ip().await?;
     let listener = crate::util::listen_to_free_port(&agent_api_host);
@@ -26,24 +65,67 @@ pub async fn run<P: AsRef<Path>>(
         "http://host.docker.internal:{agent_api_port}/api/agent/git"
     ))
     .expect("Failed to parse URL");
-    let minion_api_base_url = format!("http://host.docker.internal:{agent_api_port}/api/");
+    let minion_api_base_url = Url::parse(&format!(
+        "http://host.docker.internal:{agent_api_port}/api/"
+    ))
+    .expect("Failed to parse URL");
     let fork_branch = Uuid::now_v7().to_string();
     let agent_api_key = context::random_key();
     let host_address = format!("http://{agent_api_host}:{agent_api_port}");
 
     let base_branch = current_branch_name(path)?;
+    let artifacts_dir = Config::data_dir()?.join("artifacts").join(&fork_branch);
+
+    let run_span = tracing::info_span!("minion run", task.branch = %fork_branch);
 
     create_git_branch(path, &fork_branch)?;
 
+    let events = Arc::new(EventHub::new());
+    let mut console = if options.json {
+        Console::stderr_only()
+    } else {
+        Console::new()
+    };
+    if let Some(prefix) = options.output_prefix {
+        console = console.with_prefix(prefix);
+    }
+    let console = Arc::new(console);
+    let (_, events_rx) = events.subscribe();
+    let (summary_stop_tx, summary_stop_rx) = oneshot::channel();
+    let summary = tokio::spawn(report::summarize_events(events_rx, summary_stop_rx));
+
+    let dashboard = if options.dashboard {
+        let listener = crate::util::listen_to_free_port("127.0.0.1");
+        let token = context::random_key();
+        let port = listener.local_addr()?.port();
+        eprintln!("Dashboard: http://127.0.0.1:{port}/?token={token}");
+        Some(DashboardConfig { listener, token })
+    } else {
+        None
+    };
+
     let ctx = Context {
         llm_router_table,
+        llm_response_cache,
         agent_api_key: agent_api_key.clone(),
         task_description,
         git_user_name: "minion[bot]".to_owned(),
         git_user_email: "minion@localhost".to_owned(),
+        agent_api_base_url: minion_api_base_url.clone(),
         git_repo_url,
         git_branch: fork_branch.clone(),
         git_repo_path: path.as_ref().to_path_buf(),
+        artifacts_dir: artifacts_dir.clone(),
+        events: events.clone(),
+        console: console.clone(),
+        inquiry_policy: options.inquiry_policy,
+        permission_policy: options.permission_policy,
+        mcp_servers: options.mcp_servers,
+        mode: options.mode,
+        model: options.model,
+        attachments: options.attachments,
+        references: options.references,
+        acceptance_criteria: options.acceptance_criteria,
     };
 
     let image = if let Some(containerfile) = containerfile {
@@ -53,24 +135,45 @@ pub async fn run<P: AsRef<Path>>(
         AGENT_CONTAINER_IMAGE.to_owned()
     };
 
+    let mut env_vars = vec![
+        (
+            "MINION_API_BASE_URL".to_owned(),
+            minion_api_base_url.to_string(),
+        ),
+        ("MINION_API_TOKEN".to_owned(), agent_api_key),
+    ];
+    // Let agents join the trace of this run.
+    if let Some(traceparent) = crate::telemetry::traceparent(&run_span) {
+        env_vars.push(("TRACEPARENT".to_owned(), traceparent));
+    }
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    let (kill_tx, kill_rx) = watch::channel(false);
+
     let container_config = ContainerConfig {
         image,
-        env_vars: vec![
-            ("MINION_API_BASE_URL".to_owned(), minion_api_base_url),
-            ("MINION_API_TOKEN".to_owned(), agent_api_key),
-        ],
+        env_vars,
+        console,
+        events,
+        kill: kill_rx.clone(),
     };
 
-    let server = tokio::spawn(crate::api::run_server(listener, ctx));
+    let mut server = tokio::spawn(
+        crate::api::run_server(listener, ctx, dashboard, cancel_rx).instrument(run_span),
+    );
     // Wait for the server to be ready by polling the /ready endpoint
     crate::api::wait_until_ready(&host_address).await?;
 
+    let interrupts = tokio::spawn(handle_interrupts(cancel_tx, kill_tx));
+
     let (task_outcome, container_id) = tokio::try_join!(
         async {
-            server
-                .await
-                .map_err(|e| anyhow!(e))?
-                .map_err(|e| anyhow!(e))
+            let mut killed = kill_rx;
+            tokio::select! {
+                outcome = &mut server => outcome.map_err(|e| anyhow!(e))?.map_err(|e| anyhow!(e)),
+                // A killed agent cannot report the outcome of its task.
+                Ok(_) = killed.wait_for(|killed| *killed) => Ok(TaskOutcome::Failure),
+            }
         },
         async {
             rt.run_container(container_config)
@@ -78,24 +181,86 @@ pub async fn run<P: AsRef<Path>>(
                 .map_err(|e| anyhow!(e))
         }
     )?;
+    server.abort();
+    interrupts.abort();
 
     rt.delete_container(container_id.to_string()).await?;
 
-    if task_outcome == TaskOutcome::Failure {
-        return Ok(());
+    let _ = summary_stop_tx.send(());
+    let summary = summary.await?;
+
+    let artifacts = artifacts::list(&artifacts_dir)?;
+    print_artifacts(&artifacts_dir, &artifacts);
+    let changed_files = changed_files(path, &base_branch, &fork_branch)?;
+
+    let outcome = match task_outcome {
+        TaskOutcome::Completed => Outcome::Completed,
+        TaskOutcome::Failure if summary.cancelled => Outcome::Cancelled,
+        TaskOutcome::Failure => Outcome::Failed,
+    };
+
+    let mut merged = false;
+    let mut merge_error = None;
+    if outcome != Outcome::Completed {
+        eprintln!("The agent's work so far is on the task branch: {fork_branch}");
+    } else if !options.merge {
+        eprintln!("The changes are on the task branch: {fork_branch}");
+    } else {
+        match squash_merge_branch(path, &base_branch, &fork_branch) {
+            Ok(()) => merged = true,
+            Err(err) => {
+                eprintln!();
+                eprintln!("Unable to squash-merge task branch into {base_branch}.");
+                eprintln!("Reason: {err}");
+                eprintln!("Task branch: {fork_branch}");
+                eprintln!("You could switch to the task branch:");
+                eprintln!("  git switch {fork_branch}");
+                eprintln!("Or manually squash-merge and leave changes unstaged:");
+                eprintln!("  git merge --squash {fork_branch} && git reset");
+                merge_error = Some(err.to_string());
+            }
+        }
+    }
+
+    Ok(RunReport {
+        task_id: fork_branch.clone(),
+        fork_branch,
+        outcome,
+        description: summary.description,
+        merged,
+        merge_error,
+        changed_files,
+        artifacts,
+        usage: summary.usage,
+    })
+}
+
+/// On the first Ctrl-C, cancel the task so the agent can stop and keep its work.
+/// Kill the agent on the second Ctrl-C, or if it does not stop in time.
+async fn handle_interrupts(cancel_tx: oneshot::Sender<()>, kill_tx: watch::Sender<bool>) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
     }
+    eprintln!("\nCancelling the task. Press Ctrl-C again to stop the agent immediately.");
+    let _ = cancel_tx.send(());
 
-    if let Err(err) = squash_merge_branch(path, &base_branch, &fork_branch) {
-        eprintln!();
-        eprintln!("Unable to squash-merge task branch into {base_branch}.");
-        eprintln!("Reason: {err}");
-        eprintln!("Task branch: {fork_branch}");
-        eprintln!("You could switch to the task branch:");
-        eprintln!("  git switch {fork_branch}");
-        eprintln!("Or manually squash-merge and leave changes unstaged:");
-        eprintln!("  git merge --squash {fork_branch} && git reset");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => eprintln!("\nStopping the agent."),
+        () = tokio::time::sleep(CANCEL_TIMEOUT) => {
+            eprintln!("\nThe agent did not stop in time. Stopping it.");
+        }
+    }
+    let _ = kill_tx.send(true);
+}
+
+fn print_artifacts(dir: &Path, artifacts: &[Artifact]) {
+    if artifacts.is_empty() {
+        return;
+    }
+    eprintln!("\nArtifacts of the task in {}:", dir.display());
+    for artifact in artifacts {
+        eprintln!("  {} ({} bytes)", artifact.name, artifact.size);
     }
-    Ok(())
 }
 
 /// Create a new git branch from the current HEAD.
@@ -164,6 +329,29 @@ fn squash_merge_branch<P: AsRef<Path>>(path: P, base: &str, fork: &str) -> anyho
     Ok(())
 }
 
+/// Paths changed on the fork branch since it diverged from the base branch.
+fn changed_files<P: AsRef<Path>>(path: P, base: &str, fork: &str) -> anyhow::Result<Vec<String>> {
+    let repo = git2::Repository::open(path)?;
+
+    let base_commit = repo
+        .find_branch(base, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+    let fork_commit = repo
+        .find_branch(fork, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+    let merge_base_oid = repo.merge_base(base_commit.id(), fork_commit.id())?;
+    let merge_base_tree = repo.find_commit(merge_base_oid)?.tree()?;
+
+    let diff = repo.diff_tree_to_tree(Some(&merge_base_tree), Some(&fork_commit.tree()?), None)?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
 fn current_branch_name<P: AsRef<Path>>(path: P) -> anyhow::Result<String> {
     let repo = git2::Repository::open(path)?;
 
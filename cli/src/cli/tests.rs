@@ -0,0 +1,115 @@
+//! Tests for turning the run options into policies for the run.
+#![cfg(test)]
+
+use std::fs;
+
+use agent_api::types::permission::PermissionDecision;
+
+use super::RunArgs;
+use crate::api::InquiryMode;
+
+#[test]
+fn interactive_runs_leave_permission_requests_to_the_user() {
+    let args = RunArgs::default();
+    let mode = args.inquiry_policy(true).unwrap().mode;
+    assert_eq!(mode, InquiryMode::Interactive);
+    assert!(args.permission_policy(mode).unwrap().is_none());
+}
+
+#[test]
+fn auto_answer_runs_reject_permission_requests_without_a_policy() {
+    let args = RunArgs {
+        yes: true,
+        ..RunArgs::default()
+    };
+    let mode = args.inquiry_policy(true).unwrap().mode;
+    assert_eq!(mode, InquiryMode::AutoAnswer);
+    let policy = args.permission_policy(mode).unwrap().unwrap();
+    assert_eq!(
+        policy.decide("execute", "cargo test"),
+        (PermissionDecision::Reject, None)
+    );
+}
+
+#[test]
+fn auto_answer_runs_keep_the_decisions_of_the_policy() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("permissions.toml");
+    fs::write(
+        &path,
+        "default = \"ask\"\n[[rules]]\nkind = \"execute\"\ndecision = \"allow_once\"\n",
+    )
+    .unwrap();
+    let args = RunArgs {
+        inquiry_mode: Some(InquiryMode::AutoAnswer),
+        permission_policy: Some(path),
+        ..RunArgs::default()
+    };
+    let mode = args.inquiry_policy(true).unwrap().mode;
+    let policy = args.permission_policy(mode).unwrap().unwrap();
+    assert_eq!(
+        policy.decide("execute", "cargo test"),
+        (PermissionDecision::AllowOnce, Some(0))
+    );
+    assert_eq!(
+        policy.decide("edit", "src/main.rs"),
+        (PermissionDecision::Reject, None)
+    );
+}
+
+#[test]
+fn batch_runs_are_not_interactive() {
+    let args = RunArgs::default();
+    assert_eq!(
+        args.batch_inquiry_policy().unwrap().mode,
+        InquiryMode::AutoAnswer
+    );
+
+    let args = RunArgs {
+        inquiry_mode: Some(InquiryMode::Fail),
+        ..RunArgs::default()
+    };
+    assert_eq!(args.batch_inquiry_policy().unwrap().mode, InquiryMode::Fail);
+
+    let args = RunArgs {
+        inquiry_mode: Some(InquiryMode::Interactive),
+        ..RunArgs::default()
+    };
+    let Err(err) = args.batch_inquiry_policy() else {
+        panic!("an interactive batch run is accepted");
+    };
+    assert_eq!(err.to_string(), "Batch runs cannot be interactive.");
+}
+
+#[test]
+fn inquiries_are_auto_answered_if_stdin_cannot_answer() {
+    let piped = RunArgs::default();
+    assert_eq!(
+        piped.inquiry_policy(false).unwrap().mode,
+        InquiryMode::AutoAnswer
+    );
+
+    let from_stdin = RunArgs {
+        message: Some("-".to_owned()),
+        ..RunArgs::default()
+    };
+    assert_eq!(
+        from_stdin.inquiry_policy(true).unwrap().mode,
+        InquiryMode::AutoAnswer
+    );
+
+    let with_dashboard = RunArgs {
+        dashboard: true,
+        ..from_stdin.clone()
+    };
+    assert_eq!(
+        with_dashboard.inquiry_policy(true).unwrap().mode,
+        InquiryMode::Interactive
+    );
+
+    let interactive = RunArgs {
+        inquiry_mode: Some(InquiryMode::Interactive),
+        ..from_stdin
+    };
+    assert!(interactive.inquiry_policy(true).is_err());
+}
@@ -1,9 +1,11 @@
 use core::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 use std::{collections::HashMap, fs};
 
 use anyhow::anyhow;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use llm_proxy::ResponseCache;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use url::Url;
@@ -52,6 +54,10 @@ pub struct Config {
     pub chatgpt_access_token: Option<String>,
     pub chatgpt_refresh_token: Option<String>,
     pub chatgpt_last_refresh_unix_secs: Option<i64>,
+    /// Cache responses to deterministic LLM requests (temperature 0 or fixed seed).
+    pub llm_cache_enabled: Option<bool>,
+    pub llm_cache_ttl_secs: Option<u64>,
+    pub llm_cache_max_size_bytes: Option<u64>,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, Deserialize, Serialize)]
@@ -86,6 +92,7 @@ impl fmt::Display for LLMProvider {
     }
 }
 
+#[derive(Clone)]
 pub struct LLMRouterTable {
     pub default_provider: String,
     pub providers: HashMap<String, LLMProviderDetails>,
@@ -111,6 +118,7 @@ impl LLMRouterTable {
     }
 }
 
+#[derive(Clone)]
 pub struct LLMProviderDetails {
     pub api_chat_completions_endpoint: Url,
     pub api_responses_endpoint: Url,
@@ -163,6 +171,33 @@ impl Config {
             .join("config.toml"))
     }
 
+    pub fn cache_dir() -> anyhow::Result<PathBuf> {
+        Ok(dirs::cache_dir()
+            .ok_or(anyhow!("Failed to locate appropriate cache directory"))?
+            .join("minion"))
+    }
+
+    pub fn data_dir() -> anyhow::Result<PathBuf> {
+        Ok(dirs::data_dir()
+            .ok_or(anyhow!("Failed to locate appropriate data directory"))?
+            .join("minion"))
+    }
+
+    /// The LLM response cache, if enabled in the config.
+    pub fn llm_response_cache(&self) -> anyhow::Result<Option<ResponseCache>> {
+        if !self.llm_cache_enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+        let mut cache = ResponseCache::new(Self::cache_dir()?.join("llm-responses"));
+        if let Some(ttl_secs) = self.llm_cache_ttl_secs {
+            cache = cache.ttl(Duration::from_secs(ttl_secs));
+        }
+        if let Some(max_size_bytes) = self.llm_cache_max_size_bytes {
+            cache = cache.max_size_bytes(max_size_bytes);
+        }
+        Ok(Some(cache))
+    }
+
     pub fn llm_router_table(&self) -> Option<LLMRouterTable> {
         let mut providers = HashMap::new();
 
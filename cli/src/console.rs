@@ -0,0 +1,106 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use crate::events::LogStream;
+
+/// Terminal output of the agent's container.
+///
+/// Output can be paused, e.g. while the user is answering an inquiry.
+/// Paused output is buffered and printed once output is resumed.
+pub struct Console {
+    state: Mutex<ConsoleState>,
+    /// Write stdout of the container to stderr, e.g. to keep stdout machine-readable.
+    stderr_only: bool,
+    /// Written before each line, to tell apart the output of tasks that run side by side.
+    prefix: Option<String>,
+}
+
+#[derive(Default)]
+struct ConsoleState {
+    paused: bool,
+    buffered: Vec<(LogStream, String)>,
+    /// The last output did not end with a newline.
+    mid_line: bool,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(ConsoleState::default()),
+            stderr_only: false,
+            prefix: None,
+        }
+    }
+
+    /// A console that writes all output to stderr.
+    pub fn stderr_only() -> Self {
+        Self {
+            stderr_only: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn print(&self, stream: LogStream, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused {
+            state.buffered.push((stream, text.to_owned()));
+        } else {
+            self.write_to_terminal(&mut state, stream, text);
+        }
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.paused {
+            state.paused = true;
+            let _ = io::stdout().flush();
+            let _ = io::stderr().flush();
+        }
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = false;
+        for (stream, text) in std::mem::take(&mut state.buffered) {
+            self.write_to_terminal(&mut state, stream, &text);
+        }
+    }
+
+    fn write_to_terminal(&self, state: &mut ConsoleState, stream: LogStream, text: &str) {
+        let prefixed;
+        let text = match &self.prefix {
+            Some(prefix) => {
+                prefixed = prefix_lines(prefix, text, &mut state.mid_line);
+                &prefixed
+            }
+            None => text,
+        };
+        match stream {
+            LogStream::Stdout if !self.stderr_only => {
+                print!("{text}");
+                let _ = io::stdout().flush();
+            }
+            LogStream::Stdout | LogStream::Stderr => {
+                eprint!("{text}");
+                let _ = io::stderr().flush();
+            }
+        }
+    }
+}
+
+fn prefix_lines(prefix: &str, text: &str, mid_line: &mut bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if !*mid_line {
+            out.push_str(prefix);
+        }
+        out.push_str(line);
+        *mid_line = !line.ends_with('\n');
+    }
+    out
+}
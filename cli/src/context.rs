@@ -1,11 +1,22 @@
+use std::sync::Arc;
+
+use agent_api::types::mcp::McpServer;
+use agent_api::types::permission::PermissionPolicy;
+use agent_api::types::task::{Attachment, FileReference};
+use llm_proxy::ResponseCache;
 use rand::{distr::Alphanumeric, RngExt as _};
 use url::Url;
 
+use crate::api::InquiryPolicy;
 use crate::config::LLMRouterTable;
+use crate::console::Console;
+use crate::events::EventHub;
 
 pub struct Context {
     /// LLM API configuration and secrets.
     pub llm_router_table: LLMRouterTable,
+    /// Cache for responses to deterministic LLM requests, if enabled.
+    pub llm_response_cache: Option<ResponseCache>,
     /// Randomly generated key supplied to the agent.
     pub agent_api_key: String,
     /// The user's task description.
@@ -16,6 +27,9 @@ pub struct Context {
     /// The git email to use for commits.
     /// This is *not* the email of the user, but a machine-generated email.
     pub git_user_email: String,
+    /// Base URL of the host API.
+    /// Valid inside the agent's container.
+    pub agent_api_base_url: Url,
     /// The git repository URL for the agent to clone.
     /// Valid inside the agent's container.
     pub git_repo_url: Url,
@@ -23,6 +37,28 @@ pub struct Context {
     pub git_branch: String,
     /// The path to the git repository on the host machine.
     pub git_repo_path: std::path::PathBuf,
+    /// Where the agent's artifacts are stored, one directory per task.
+    pub artifacts_dir: std::path::PathBuf,
+    /// Events of the run, e.g. for display on the dashboard.
+    pub events: Arc<EventHub>,
+    /// Terminal output of the agent's container.
+    pub console: Arc<Console>,
+    /// How to handle inquiries of the agent.
+    pub inquiry_policy: InquiryPolicy,
+    /// How the agent's permission requests are answered, if set by the user.
+    pub permission_policy: Option<PermissionPolicy>,
+    /// MCP servers declared by the user. HTTP servers are proxied by the host API.
+    pub mcp_servers: Vec<McpServer>,
+    /// Session mode requested by the user, e.g. `plan`.
+    pub mode: Option<String>,
+    /// Model requested by the user.
+    pub model: Option<String>,
+    /// Files the user attached to the task.
+    pub attachments: Vec<Attachment>,
+    /// Files or line ranges in the repository that the task is about.
+    pub references: Vec<FileReference>,
+    /// Conditions the result must meet for the task to be done.
+    pub acceptance_criteria: Vec<String>,
 }
 
 /// Generate a random API key.
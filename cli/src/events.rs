@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use agent_api::types::task::TaskProgress;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Number of past events kept for observers that connect late.
+const HISTORY_CAPACITY: usize = 2000;
+
+/// Something noteworthy that happened while running a task.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Output of the agent's container.
+    Log {
+        stream: LogStream,
+        text: String,
+    },
+    /// A request forwarded to an LLM provider.
+    LlmCall {
+        api: &'static str,
+        model: Option<String>,
+        streamed: bool,
+        prompt_tokens: Option<u64>,
+        completion_tokens: Option<u64>,
+    },
+    /// The token usage reported at the end of a streamed LLM response.
+    LlmUsage {
+        prompt_tokens: u64,
+        completion_tokens: u64,
+    },
+    /// The agent reported progress on its task.
+    Progress(TaskProgress),
+    /// The user sent a message to the agent.
+    Message {
+        id: u64,
+        message: String,
+    },
+    /// The agent pushed to its task branch.
+    GitPush {
+        git_ref: String,
+    },
+    /// The agent asked a question.
+    Inquiry {
+        id: u64,
+        question: String,
+    },
+    /// A question of the agent was answered.
+    InquiryAnswered {
+        id: u64,
+        answer: String,
+    },
+    /// The agent stored an artifact for the user.
+    Artifact {
+        name: String,
+        size: u64,
+    },
+    /// The user asked the agent to stop.
+    TaskCancelled,
+    TaskCompleted {
+        description: String,
+    },
+    TaskFailed {
+        description: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Fan-out of run events to any number of observers, e.g. the web dashboard.
+pub struct EventHub {
+    tx: broadcast::Sender<Event>,
+    history: Mutex<VecDeque<Event>>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(HISTORY_CAPACITY);
+        Self {
+            tx,
+            history: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let mut history = self.history.lock().unwrap();
+        if history.len() == HISTORY_CAPACITY {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+        // Sending only fails if there are no subscribers, which is fine.
+        let _ = self.tx.send(event);
+    }
+
+    /// Subscribe to future events. Also returns all past events that are still retained.
+    pub fn subscribe(&self) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        // Hold the history lock so that no event is missed or duplicated.
+        let history = self.history.lock().unwrap();
+        (history.iter().cloned().collect(), self.tx.subscribe())
+    }
+}
@@ -1,11 +1,14 @@
 mod api;
 mod cli;
 mod config;
+mod console;
 mod context;
+mod events;
 mod providers;
 mod runtime;
+mod telemetry;
 mod util;
 
-pub fn main() {
-    cli::exec();
+pub fn main() -> std::process::ExitCode {
+    cli::exec()
 }
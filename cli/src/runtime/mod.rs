@@ -10,11 +10,22 @@ use bollard::query_parameters::{
 use bollard::Docker;
 use futures::StreamExt;
 use futures::TryStreamExt;
+use std::sync::Arc;
+use tokio::sync::watch;
 use uuid::Uuid;
 
+use crate::console::Console;
+use crate::events::{Event, EventHub, LogStream};
+
 pub struct ContainerConfig {
     pub image: String,
     pub env_vars: Vec<(String, String)>,
+    /// Terminal to which the container's output is forwarded.
+    pub console: Arc<Console>,
+    /// Receives the container's output in addition to the terminal.
+    pub events: Arc<EventHub>,
+    /// The container is killed once this turns `true`.
+    pub kill: watch::Receiver<bool>,
 }
 
 /// Runtime that uses the local Docker daemon to run containers.
@@ -123,7 +134,7 @@ impl LocalDockerRuntime {
 
         while let Some(build_result) = build_stream.try_next().await? {
             if let Some(output) = build_result.stream {
-                print!("{output}");
+                eprint!("{output}");
             }
         }
 
@@ -190,20 +201,18 @@ impl LocalDockerRuntime {
         let mut output_stream = attached.output;
 
         // Spawn a task to forward container output (stdout/stderr) to host stdout.
+        let console = config.console;
+        let events = config.events;
         let output_forwarder = tokio::spawn(async move {
             while let Some(Ok(log)) = output_stream.next().await {
-                match log {
-                    LogOutput::StdOut { message } => {
-                        if let Ok(text) = String::from_utf8(message.to_vec()) {
-                            print!("{text}");
-                        }
-                    }
-                    LogOutput::StdErr { message } => {
-                        if let Ok(text) = String::from_utf8(message.to_vec()) {
-                            eprint!("{text}");
-                        }
-                    }
-                    _ => {}
+                let (stream, message) = match log {
+                    LogOutput::StdOut { message } => (LogStream::Stdout, message),
+                    LogOutput::StdErr { message } => (LogStream::Stderr, message),
+                    _ => continue,
+                };
+                if let Ok(text) = String::from_utf8(message.to_vec()) {
+                    console.print(stream, &text);
+                    events.publish(Event::Log { stream, text });
                 }
             }
         });
@@ -213,17 +222,31 @@ impl LocalDockerRuntime {
             .docker
             .wait_container(&container.id, None::<WaitContainerOptions>);
 
-        if let Some(result) = wait_stream.next().await {
-            let wait_msg = result?;
-            if wait_msg.status_code > 0 {
-                return Err(anyhow::anyhow!(
-                    "Container exited with status code {}",
-                    wait_msg.status_code
-                ));
+        let mut kill = config.kill;
+        let killed = tokio::select! {
+            result = wait_stream.next() => {
+                if let Some(result) = result {
+                    let wait_msg = result?;
+                    if wait_msg.status_code > 0 {
+                        return Err(anyhow::anyhow!(
+                            "Container exited with status code {}",
+                            wait_msg.status_code
+                        ));
+                    }
+                }
+                false
             }
-        }
+            Ok(_) = kill.wait_for(|kill| *kill) => true,
+        };
 
-        let _ = output_forwarder.await;
+        if killed {
+            self.docker.kill_container(&container.id, None).await?;
+            // Wait until the container is gone, so it can be deleted.
+            let _ = wait_stream.next().await;
+            output_forwarder.abort();
+        } else {
+            let _ = output_forwarder.await;
+        }
 
         Ok(container.id)
     }
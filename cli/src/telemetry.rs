@@ -0,0 +1,56 @@
+//! Opt-in distributed tracing via OpenTelemetry.
+//!
+//! When enabled, spans are exported via OTLP over HTTP. The exporter is configured through the
+//! standard `OTEL_EXPORTER_OTLP_*` environment variables (e.g. `OTEL_EXPORTER_OTLP_ENDPOINT`).
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt as _;
+
+/// Handle to the installed tracer provider.
+/// Call [`Telemetry::shutdown`] before exiting to flush pending spans.
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Install a global tracing subscriber that exports spans via OTLP.
+    pub fn init() -> anyhow::Result<Self> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("minion").build())
+            .build();
+
+        let subscriber = tracing_subscriber::registry()
+            .with(LevelFilter::INFO)
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("minion")));
+        tracing::subscriber::set_global_default(subscriber)?;
+
+        Ok(Self { provider })
+    }
+
+    pub fn shutdown(self) {
+        if let Err(err) = self.provider.shutdown() {
+            log::warn!("Failed to flush telemetry: {err}");
+        }
+    }
+}
+
+/// The W3C `traceparent` header value for the given span.
+///
+/// Returns `None` if tracing is not enabled.
+pub fn traceparent(span: &tracing::Span) -> Option<String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&span.context(), &mut carrier);
+    carrier.remove("traceparent")
+}
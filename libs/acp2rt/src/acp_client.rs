@@ -1,58 +1,120 @@
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::io;
+use std::sync::{Arc, Mutex};
 
+use agent_api::Client as AgentApiClient;
+use agent_api::types::permission::PermissionPolicy;
+use agent_api::types::task::{
+    PlanEntry as TaskPlanEntry, PlanEntryStatus as TaskPlanEntryStatus, TaskProgress,
+};
 use agent_client_protocol::*;
 use async_trait::async_trait;
+use tokio::task::JoinHandle;
 
-use crate::AcpResult;
+use crate::git::Repository;
+use crate::permission;
+use crate::render::{OutputFormat, Renderer};
+use crate::terminal::Terminals;
+use crate::{AcpResult, Sandbox};
 
 #[derive(Debug, Clone)]
 pub struct ACPClient {
-    root: PathBuf,
+    sandbox: Sandbox,
+    api: AgentApiClient,
+    policy: PermissionPolicy,
+    terminals: Arc<Terminals>,
+    /// Text the agent sent since the last call to [`ACPClient::take_agent_message`].
+    agent_message: Arc<Mutex<String>>,
+    /// Where to commit checkpoints, if enabled.
+    checkpoints: Option<Repository>,
+    /// Plan entries that were completed so far.
+    completed_entries: Arc<Mutex<HashSet<String>>>,
+    /// The checkpoint that was taken last, which may still be running.
+    last_checkpoint: Arc<Mutex<Option<JoinHandle<()>>>>,
+    renderer: Arc<Renderer>,
 }
 
 impl ACPClient {
-    pub fn new(root: PathBuf) -> Self {
-        Self { root }
+    pub fn new(sandbox: Sandbox, api: AgentApiClient, policy: PermissionPolicy) -> Self {
+        Self {
+            sandbox,
+            api,
+            policy,
+            terminals: Arc::default(),
+            agent_message: Arc::default(),
+            checkpoints: None,
+            completed_entries: Arc::default(),
+            last_checkpoint: Arc::default(),
+            renderer: Arc::default(),
+        }
+    }
+
+    /// How session updates of the agent are written to the console.
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.renderer = Arc::new(Renderer::new(format));
+        self
+    }
+
+    /// Commit and push to the repository whenever a plan entry is completed.
+    pub(crate) fn checkpoints(mut self, repository: Repository) -> Self {
+        self.checkpoints = Some(repository);
+        self
     }
 
-    fn resolve_path(&self, path: &Path) -> PathBuf {
-        if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.root.join(path)
+    /// Take the text the agent sent so far, e.g. its final message of a turn.
+    pub fn take_agent_message(&self) -> String {
+        std::mem::take(&mut *self.agent_message.lock().unwrap())
+    }
+
+    /// Wait until all checkpoints are committed and pushed.
+    pub(crate) async fn finish_checkpoints(&self) {
+        let last_checkpoint = self.last_checkpoint.lock().unwrap().take();
+        if let Some(last_checkpoint) = last_checkpoint {
+            let _ = last_checkpoint.await;
         }
     }
 }
 
 #[async_trait(?Send)]
 impl Client for ACPClient {
+    /// Permission requests are the only questions ACP lets an agent ask the client; the protocol
+    /// version used here has no elicitation requests. Requests the policy leaves to the user are
+    /// sent to the runtime as inquiries.
     async fn request_permission(
         &self,
         args: RequestPermissionRequest,
     ) -> AcpResult<RequestPermissionResponse> {
-        let outcome = args
-            .options
-            .first()
-            .map(|option| {
-                RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
-                    option.option_id.clone(),
-                ))
-            })
-            .unwrap_or(RequestPermissionOutcome::Cancelled);
+        let outcome = match permission::choose_option(&self.policy, &self.api, &args).await {
+            Some(option) => RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+                option.option_id.clone(),
+            )),
+            None => RequestPermissionOutcome::Cancelled,
+        };
         Ok(RequestPermissionResponse::new(outcome))
     }
 
     async fn session_notification(&self, args: SessionNotification) -> AcpResult<()> {
-        self.forward_session_notification(&args);
+        self.record_agent_message(&args.update);
+        self.renderer.render(&args, &self.terminals);
+        if let SessionUpdate::Plan(plan) = &args.update {
+            if let Err(err) = self.api.report_progress(plan_progress(plan)).await {
+                eprintln!("[plan] failed to report progress: {err}");
+            }
+            self.checkpoint_completed_entries(plan);
+        }
         Ok(())
     }
 
     async fn read_text_file(&self, args: ReadTextFileRequest) -> AcpResult<ReadTextFileResponse> {
-        let path = self.resolve_path(&args.path);
+        let path = self.sandbox.resolve_read(&args.path)?;
         let content = tokio::fs::read_to_string(&path)
             .await
-            .map_err(anyhow::Error::from)?;
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => {
+                    Error::resource_not_found(Some(args.path.display().to_string()))
+                }
+                _ => Error::into_internal_error(err),
+            })?;
         let sliced = slice_lines(content, args.line, args.limit);
         Ok(ReadTextFileResponse::new(sliced))
     }
@@ -61,7 +123,7 @@ impl Client for ACPClient {
         &self,
         args: WriteTextFileRequest,
     ) -> AcpResult<WriteTextFileResponse> {
-        let path = self.resolve_path(&args.path);
+        let path = self.sandbox.resolve_write(&args.path)?;
         if let Some(parent) = path.parent() {
             tokio::fs::create_dir_all(parent)
                 .await
@@ -72,74 +134,132 @@ impl Client for ACPClient {
             .map_err(anyhow::Error::from)?;
         Ok(WriteTextFileResponse::new())
     }
+
+    async fn create_terminal(
+        &self,
+        args: CreateTerminalRequest,
+    ) -> AcpResult<CreateTerminalResponse> {
+        let cwd = match &args.cwd {
+            Some(cwd) => self.sandbox.resolve_write(cwd)?,
+            None => self.sandbox.root().to_path_buf(),
+        };
+        eprintln!("[terminal] {} {}", args.command, args.args.join(" "));
+        let terminal_id = self.terminals.create(&args, &cwd)?;
+        Ok(CreateTerminalResponse::new(terminal_id))
+    }
+
+    async fn terminal_output(
+        &self,
+        args: TerminalOutputRequest,
+    ) -> AcpResult<TerminalOutputResponse> {
+        let (output, truncated, exit_status) = self.terminals.output(&args.terminal_id)?;
+        Ok(TerminalOutputResponse::new(output, truncated).exit_status(exit_status))
+    }
+
+    async fn release_terminal(
+        &self,
+        args: ReleaseTerminalRequest,
+    ) -> AcpResult<ReleaseTerminalResponse> {
+        self.terminals.release(&args.terminal_id)?;
+        Ok(ReleaseTerminalResponse::new())
+    }
+
+    async fn wait_for_terminal_exit(
+        &self,
+        args: WaitForTerminalExitRequest,
+    ) -> AcpResult<WaitForTerminalExitResponse> {
+        let exit_status = self.terminals.wait_for_exit(&args.terminal_id).await?;
+        Ok(WaitForTerminalExitResponse::new(exit_status))
+    }
+
+    async fn kill_terminal_command(
+        &self,
+        args: KillTerminalCommandRequest,
+    ) -> AcpResult<KillTerminalCommandResponse> {
+        self.terminals.kill(&args.terminal_id)?;
+        Ok(KillTerminalCommandResponse::new())
+    }
 }
 
 impl ACPClient {
-    fn forward_session_notification(&self, notification: &SessionNotification) {
-        match &notification.update {
-            SessionUpdate::UserMessageChunk(chunk) | SessionUpdate::AgentMessageChunk(chunk) => {
-                forward_content_to_stdout(&chunk.content);
-            }
-            SessionUpdate::AgentThoughtChunk(chunk) => {
-                forward_content_to_stderr(&chunk.content);
-            }
-            SessionUpdate::ToolCall(tool_call) => {
-                forward_tool_call(tool_call);
+    /// Commit and push in the background, so that the agent's messages are processed meanwhile.
+    /// Must be called from within a `LocalSet`.
+    fn checkpoint_completed_entries(&self, plan: &Plan) {
+        let Some(repository) = self.checkpoints.clone() else {
+            return;
+        };
+        let newly_completed: Vec<String> = {
+            let mut completed = self.completed_entries.lock().unwrap();
+            plan.entries
+                .iter()
+                .filter(|entry| entry.status == PlanEntryStatus::Completed)
+                .filter(|entry| completed.insert(entry.content.clone()))
+                .map(|entry| entry.content.clone())
+                .collect()
+        };
+        if newly_completed.is_empty() {
+            return;
+        }
+        let mut last_checkpoint = self.last_checkpoint.lock().unwrap();
+        let previous = last_checkpoint.take();
+        *last_checkpoint = Some(tokio::task::spawn_local(async move {
+            // Checkpoints are taken one after another, so that their git commands don't clash.
+            if let Some(previous) = previous {
+                let _ = previous.await;
             }
-            SessionUpdate::Plan(plan) => {
-                if plan.entries.is_empty() {
-                    eprintln!("[plan] cleared");
-                } else {
-                    let mut pending = 0usize;
-                    let mut in_progress = 0usize;
-                    let mut completed = 0usize;
-                    for entry in &plan.entries {
-                        match entry.status {
-                            agent_client_protocol::PlanEntryStatus::Pending => pending += 1,
-                            agent_client_protocol::PlanEntryStatus::InProgress => in_progress += 1,
-                            agent_client_protocol::PlanEntryStatus::Completed => completed += 1,
-                            _ => (),
-                        }
-                    }
-                    eprintln!(
-                        "[plan] {} item(s): {} pending, {} in progress, {} completed",
-                        plan.entries.len(),
-                        pending,
-                        in_progress,
-                        completed
-                    );
-                }
+            if let Err(err) = repository.checkpoint(&newly_completed.join("; ")).await {
+                eprintln!("[checkpoint] failed to commit and push: {err}");
             }
-            _ => (),
-        }
+        }));
     }
-}
 
-fn forward_tool_call(tool_call: &ToolCall) {
-    eprintln!("\n[tool call] {}", tool_call.title);
-}
-
-fn forward_content_to_stdout(content: &ContentBlock) {
-    match content {
-        ContentBlock::Text(text) => {
-            print!("{}", text.text);
-            let _ = io::stdout().flush();
-        }
-        other => {
-            println!("{other:?}");
+    fn record_agent_message(&self, update: &SessionUpdate) {
+        if let SessionUpdate::AgentMessageChunk(ContentChunk {
+            content: ContentBlock::Text(text),
+            ..
+        }) = update
+        {
+            self.agent_message.lock().unwrap().push_str(&text.text);
         }
     }
 }
 
-fn forward_content_to_stderr(content: &ContentBlock) {
-    match content {
-        ContentBlock::Text(text) => {
-            eprint!("{}", text.text);
-            let _ = io::stderr().flush();
-        }
-        other => {
-            eprintln!("{other:?}");
-        }
+/// Map an ACP plan onto a progress report for the runtime.
+fn plan_progress(plan: &Plan) -> TaskProgress {
+    let entries: Vec<TaskPlanEntry> = plan
+        .entries
+        .iter()
+        .map(|entry| TaskPlanEntry {
+            title: entry.content.clone(),
+            status: match entry.status {
+                PlanEntryStatus::InProgress => TaskPlanEntryStatus::InProgress,
+                PlanEntryStatus::Completed => TaskPlanEntryStatus::Completed,
+                _ => TaskPlanEntryStatus::Pending,
+            },
+        })
+        .collect();
+
+    let completed = entries
+        .iter()
+        .filter(|entry| entry.status == TaskPlanEntryStatus::Completed)
+        .count();
+    let percent = (!entries.is_empty()).then(|| (completed * 100 / entries.len()) as u8);
+    let message = entries
+        .iter()
+        .find(|entry| entry.status == TaskPlanEntryStatus::InProgress)
+        .map(|entry| entry.title.clone())
+        .unwrap_or_else(|| {
+            if entries.is_empty() {
+                "Plan cleared".to_owned()
+            } else {
+                format!("{completed} of {} plan entries completed", entries.len())
+            }
+        });
+
+    TaskProgress {
+        message,
+        percent,
+        plan: entries,
     }
 }
 
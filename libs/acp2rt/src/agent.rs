@@ -1,41 +1,114 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use agent_api::Client as AgentApiClient;
-use agent_api::types::task::{Task, TaskComplete, TaskFailure, TaskFailureReason};
+use agent_api::types::info::Capabilities;
+use agent_api::types::mcp::McpServer;
+use agent_api::types::permission::PermissionPolicy;
+use agent_api::types::session::SessionState;
+use agent_api::types::task::{Task, TaskComplete, TaskFailure, TaskFailureReason, TaskStatus};
 use agent_client_protocol::{
-    Agent as AcpAgent, ClientCapabilities, ClientSideConnection, ContentBlock,
-    FileSystemCapability, Implementation, InitializeRequest, NewSessionRequest, PromptRequest,
-    ProtocolVersion, SessionId, TextContent,
+    Agent as AcpAgent, CancelNotification, ClientCapabilities, ClientSideConnection, ContentBlock,
+    EnvVariable, ErrorCode, FileSystemCapability, HttpHeader, Implementation, InitializeRequest,
+    LoadSessionRequest, McpServer as AcpMcpServer, McpServerHttp, McpServerStdio,
+    NewSessionRequest, PromptRequest, PromptResponse, ProtocolVersion, SessionId, SessionModeState,
+    SessionModelState, SetSessionModeRequest, SetSessionModelRequest, StopReason, TextContent,
 };
 use anyhow::{Context, Result};
 use tokio::process::Command as TokioCommand;
 use tokio::task::{JoinHandle, LocalSet};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-use crate::{ACPClient, AcpResult, AgentConfig};
+use crate::git::{Repository, commit_message, run_git};
+use crate::prompt::task_prompt;
+use crate::{ACPClient, AcpResult, AgentConfig, OutputFormat, Sandbox};
+
+mod tests;
+
+/// Polling interval of the worker loop right after a task, doubled while no task is available.
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Upper limit for the number of prompts sent in a single session.
+const MAX_TURNS: usize = 50;
+/// Sent when the agent's turn was cut short, e.g. by a token limit.
+const CONTINUE_PROMPT: &str = "Please continue working on the task.";
+/// How often to check whether the task was cancelled while the agent is working.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long the agent has to end its turn after being asked to stop.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(30);
+/// Sent after resuming the session of a previous run that was interrupted.
+const RESUME_PROMPT: &str = "The session was interrupted and has been resumed. Please check the \
+    state of the workspace and continue working on the task.";
 
 pub struct Agent {
     api: AgentApiClient,
     api_token: String,
     command_factory: Arc<dyn Fn() -> TokioCommand + Send + Sync>,
     workspace_path: PathBuf,
+    permission_policy: PermissionPolicy,
+    readable_paths: Vec<PathBuf>,
+    checkpoints: bool,
+    mcp_servers: Vec<McpServer>,
+    output_format: OutputFormat,
 }
 
 impl Agent {
     pub fn new(config: AgentConfig) -> Self {
         let api_token = config.api_token;
         Self {
-            api: AgentApiClient::new(config.api_base_url, api_token.clone()),
+            api: AgentApiClient::builder(config.api_base_url, api_token.clone())
+                .user_agent(concat!("acp2rt/", env!("CARGO_PKG_VERSION")))
+                .retry_policy(config.api_retry_policy)
+                .build()
+                .expect("Failed to build agent API client"),
             api_token,
             command_factory: config.acp_command,
             workspace_path: config.workspace_path,
+            permission_policy: config.permission_policy,
+            readable_paths: config.readable_paths,
+            checkpoints: config.checkpoints,
+            mcp_servers: config.mcp_servers,
+            output_format: config.output_format,
         }
     }
 
     pub async fn run_once(&self) -> Result<RunOutcome> {
         let task = self.api.get_task().await?;
-        let workspace = self.setup_workspace(&task).await?;
+        self.run_task(task, &self.workspace_path).await
+    }
+
+    /// Keep polling the runtime for queued tasks and work on them one after another.
+    ///
+    /// Each task gets a fresh workspace below the configured workspace path, which is removed
+    /// once the task is done. Polling backs off while no task is available.
+    pub async fn run_loop(&self) -> Result<()> {
+        let mut poll_interval = MIN_POLL_INTERVAL;
+        let mut tasks = 0u64;
+        loop {
+            match self.api.claim_task().await {
+                Ok(task) if task.status == TaskStatus::Queued => {
+                    poll_interval = MIN_POLL_INTERVAL;
+                    tasks += 1;
+                    let workspace = self.workspace_path.join(format!("task-{tasks}"));
+                    // Left over from a previous worker that did not clean up, e.g. after a crash.
+                    remove_workspace(&workspace).await?;
+                    if let Err(err) = self.run_task(task, &workspace).await {
+                        eprintln!("[worker] task failed: {err:#}");
+                    }
+                    remove_workspace(&workspace).await?;
+                    continue;
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("[worker] failed to poll for a task: {err}"),
+            }
+            tokio::time::sleep(poll_interval).await;
+            poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    async fn run_task(&self, task: Task, workspace: &Path) -> Result<RunOutcome> {
+        let workspace = self.setup_workspace(&task, workspace).await?;
 
         let outcome = {
             let local = LocalSet::new();
@@ -44,15 +117,21 @@ impl Agent {
 
         match outcome {
             Ok(outcome) => {
-                self.commit_and_push(&outcome.task).await?;
-                let description = format!("Completed task via ACP session {}", outcome.session_id);
-                let payload = TaskComplete { description };
-                self.api.complete_task(payload).await?;
+                // Also keep the agent's work if it did not finish, so it can be inspected.
+                let repository =
+                    Repository::new(outcome.workspace.clone(), outcome.task.git_branch.clone());
+                let message = commit_message(&outcome.task.description, &outcome.final_message);
+                repository.commit(&message, true).await?;
+                repository.push().await?;
+                match outcome.task_result() {
+                    Ok(payload) => self.api.complete_task(payload).await?,
+                    Err(payload) => self.api.fail_task(payload).await?,
+                }
                 Ok(outcome)
             }
             Err(err) => {
                 let description = format!("Task failed: {err}");
-                let reason = Some(TaskFailureReason::TechnicalIssues);
+                let reason = Some(failure_reason(&err));
                 let payload = TaskFailure {
                     reason,
                     description,
@@ -64,26 +143,74 @@ impl Agent {
     }
 
     async fn run_prompt(&self, task: Task, workspace: PathBuf) -> Result<RunOutcome> {
-        let (connection, mut child, io_handle) = self.spawn_acp(&workspace).await?;
+        let policy = task
+            .permission_policy
+            .clone()
+            .unwrap_or_else(|| self.permission_policy.clone());
+        let checkpoints = self
+            .checkpoints
+            .then(|| Repository::new(workspace.clone(), task.git_branch.clone()));
+        let (connection, client, mut child, io_handle) =
+            self.spawn_acp(&workspace, policy, checkpoints).await?;
         let init = InitializeRequest::new(ProtocolVersion::LATEST)
             .client_capabilities(
-                ClientCapabilities::new().fs(FileSystemCapability::new()
-                    .read_text_file(true)
-                    .write_text_file(true)),
+                ClientCapabilities::new()
+                    .fs(FileSystemCapability::new()
+                        .read_text_file(true)
+                        .write_text_file(true))
+                    .terminal(true),
             )
             .client_info(Implementation::new("acp2rt", env!("CARGO_PKG_VERSION")));
-        let _ = connection.initialize(init).await?;
+        let init = connection.initialize(init).await?;
 
-        let session = connection
-            .new_session(NewSessionRequest::new(&workspace))
+        let mcp_servers = self.mcp_servers(&task, init.agent_capabilities.mcp_capabilities.http);
+        let runtime = match self.api.capabilities().await {
+            Ok(capabilities) => capabilities,
+            Err(err) => {
+                eprintln!("[runtime] failed to fetch capabilities: {err}");
+                Capabilities::default()
+            }
+        };
+        let resumable = init.agent_capabilities.load_session && runtime.session;
+        let resumed = if resumable {
+            self.resume_session(&connection, &workspace, mcp_servers.clone())
+                .await
+        } else {
+            None
+        };
+        let (session_id, prompt, modes, models) = match resumed {
+            Some((session_id, modes, models)) => {
+                let prompt = vec![ContentBlock::Text(TextContent::new(RESUME_PROMPT))];
+                (session_id, prompt, modes, models)
+            }
+            None => {
+                let session = connection
+                    .new_session(NewSessionRequest::new(&workspace).mcp_servers(mcp_servers))
+                    .await?;
+                if resumable {
+                    let state = SessionState {
+                        session_id: session.session_id.to_string(),
+                    };
+                    if let Err(err) = self.api.save_session(state).await {
+                        eprintln!("[session] failed to save session: {err}");
+                    }
+                }
+                (
+                    session.session_id,
+                    task_prompt(&task, &init.agent_capabilities.prompt_capabilities),
+                    session.modes,
+                    session.models,
+                )
+            }
+        };
+        self.configure_session(&connection, &session_id, &task, modes, models)
             .await?;
-        let session_id = session.session_id;
-        let prompt = vec![ContentBlock::Text(TextContent::new(
-            task.description.clone(),
-        ))];
-        let response = connection
-            .prompt(PromptRequest::new(session_id.clone(), prompt))
+        let (response, cancelled) = self
+            .run_turns(&connection, &client, &session_id, prompt, &runtime)
             .await?;
+        let final_message = client.take_agent_message();
+        // The final commit must not clash with a checkpoint that is still being taken.
+        client.finish_checkpoints().await;
 
         if let Err(err) = child.kill().await
             && err.kind() != std::io::ErrorKind::InvalidInput
@@ -99,17 +226,256 @@ impl Agent {
             workspace,
             session_id,
             prompt_response: response,
+            final_message,
+            cancelled,
         })
     }
 
-    async fn setup_workspace(&self, task: &Task) -> Result<PathBuf> {
-        if self.workspace_path.exists() {
-            anyhow::bail!(
-                "workspace path already exists: {}",
-                self.workspace_path.display()
-            );
+    /// Load the session that a previous run of the agent saved for this task, if any.
+    ///
+    /// This only succeeds if the agent still has the session's history, which is the case after
+    /// a restart of the agent or its container, but not in a replacement container.
+    async fn resume_session(
+        &self,
+        connection: &ClientSideConnection,
+        workspace: &Path,
+        mcp_servers: Vec<AcpMcpServer>,
+    ) -> Option<(
+        SessionId,
+        Option<SessionModeState>,
+        Option<SessionModelState>,
+    )> {
+        let saved = match self.api.session().await {
+            Ok(saved) => saved?,
+            Err(err) => {
+                eprintln!("[session] failed to fetch saved session: {err}");
+                return None;
+            }
+        };
+        let session_id = SessionId::from(saved.session_id);
+        let request =
+            LoadSessionRequest::new(session_id.clone(), workspace).mcp_servers(mcp_servers);
+        match connection.load_session(request).await {
+            Ok(response) => {
+                eprintln!("[session] resumed session {session_id}");
+                Some((session_id, response.modes, response.models))
+            }
+            Err(err) => {
+                eprintln!("[session] failed to resume session {session_id}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Switch the session to the mode and model requested by the task.
+    ///
+    /// Fails if the agent does not offer them, rather than running the task differently than
+    /// requested (e.g. making changes in what should be a plan-only run).
+    async fn configure_session(
+        &self,
+        connection: &ClientSideConnection,
+        session_id: &SessionId,
+        task: &Task,
+        modes: Option<SessionModeState>,
+        models: Option<SessionModelState>,
+    ) -> Result<()> {
+        if let Some(requested) = &task.mode {
+            let available = modes.map(|modes| modes.available_modes).unwrap_or_default();
+            let mode = available
+                .iter()
+                .find(|mode| {
+                    mode.id.to_string().eq_ignore_ascii_case(requested)
+                        || mode.name.eq_ignore_ascii_case(requested)
+                })
+                .ok_or_else(|| {
+                    let ids: Vec<String> =
+                        available.iter().map(|mode| mode.id.to_string()).collect();
+                    TaskIssue(format!(
+                        "the agent does not offer the requested mode `{requested}` (available: {})",
+                        ids.join(", ")
+                    ))
+                })?;
+            connection
+                .set_session_mode(SetSessionModeRequest::new(
+                    session_id.clone(),
+                    mode.id.clone(),
+                ))
+                .await?;
+            eprintln!("[session] mode: {}", mode.name);
+        }
+        if let Some(requested) = &task.model {
+            let available = models
+                .map(|models| models.available_models)
+                .unwrap_or_default();
+            let model = available
+                .iter()
+                .find(|model| {
+                    model.model_id.to_string().eq_ignore_ascii_case(requested)
+                        || model.name.eq_ignore_ascii_case(requested)
+                })
+                .ok_or_else(|| {
+                    let ids: Vec<String> = available
+                        .iter()
+                        .map(|model| model.model_id.to_string())
+                        .collect();
+                    TaskIssue(format!(
+                        "the agent does not offer the requested model `{requested}` (available: {})",
+                        ids.join(", ")
+                    ))
+                })?;
+            connection
+                .set_session_model(SetSessionModelRequest::new(
+                    session_id.clone(),
+                    model.model_id.clone(),
+                ))
+                .await?;
+            eprintln!("[session] model: {}", model.name);
+        }
+        Ok(())
+    }
+
+    /// Prompt the agent with the task, then keep the session going as long as the user sends
+    /// follow-up messages or the agent stopped before finishing its turn.
+    ///
+    /// If the task is cancelled on the runtime, the agent is asked to end its turn and no
+    /// further prompts are sent. Returns the last response and whether the task was cancelled.
+    async fn run_turns(
+        &self,
+        connection: &ClientSideConnection,
+        client: &ACPClient,
+        session_id: &SessionId,
+        prompt: Vec<ContentBlock>,
+        runtime: &Capabilities,
+    ) -> Result<(PromptResponse, bool)> {
+        let mut prompt = prompt;
+        let mut last_message_id = None;
+        let mut turns = 0;
+        loop {
+            // Only the agent's message of the last turn is of interest.
+            client.take_agent_message();
+            let request = connection.prompt(PromptRequest::new(
+                session_id.clone(),
+                std::mem::take(&mut prompt),
+            ));
+            tokio::pin!(request);
+            let response = tokio::select! {
+                response = &mut request => response?,
+                () = self.wait_for_cancellation(), if runtime.cancellation => {
+                    eprintln!("[cancel] the task was cancelled, asking the agent to stop");
+                    connection
+                        .cancel(CancelNotification::new(session_id.clone()))
+                        .await?;
+                    let response = match tokio::time::timeout(CANCEL_GRACE_PERIOD, request).await {
+                        Ok(response) => response?,
+                        Err(_) => {
+                            eprintln!("[cancel] the agent did not end its turn in time");
+                            PromptResponse::new(StopReason::Cancelled)
+                        }
+                    };
+                    return Ok((response, true));
+                }
+            };
+            turns += 1;
+
+            let messages = if runtime.messages {
+                match self.api.task_messages(last_message_id).await {
+                    Ok(messages) => messages,
+                    Err(err) => {
+                        eprintln!("[turn] failed to fetch messages: {err}");
+                        Vec::new()
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+            if let Some(message) = messages.last() {
+                last_message_id = Some(message.id);
+            }
+
+            let text = if !messages.is_empty() {
+                messages
+                    .into_iter()
+                    .map(|message| message.message)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            } else if matches!(
+                response.stop_reason,
+                StopReason::MaxTokens | StopReason::MaxTurnRequests
+            ) {
+                CONTINUE_PROMPT.to_owned()
+            } else {
+                return Ok((response, false));
+            };
+
+            if turns >= MAX_TURNS {
+                eprintln!("[turn] giving up after {turns} turns");
+                return Ok((response, false));
+            }
+            eprintln!("[turn] {:?}, continuing with: {text}", response.stop_reason);
+            prompt = vec![ContentBlock::Text(TextContent::new(text))];
+        }
+    }
+
+    /// Resolves once the task is cancelled on the runtime.
+    async fn wait_for_cancellation(&self) {
+        loop {
+            tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+            // Failing to poll is not a reason to stop the agent, so errors are ignored.
+            if let Ok(TaskStatus::Cancelled) = self.api.task_status().await {
+                return;
+            }
+        }
+    }
+
+    /// MCP servers of the task and the configuration. The task takes precedence for servers
+    /// with the same name.
+    fn mcp_servers(&self, task: &Task, http_supported: bool) -> Vec<AcpMcpServer> {
+        let configured = self
+            .mcp_servers
+            .iter()
+            .filter(|server| task.mcp_servers.iter().all(|s| s.name() != server.name()));
+        task.mcp_servers
+            .iter()
+            .chain(configured)
+            .filter_map(|server| match server {
+                McpServer::Stdio {
+                    name,
+                    command,
+                    args,
+                    env,
+                } => Some(AcpMcpServer::Stdio(
+                    McpServerStdio::new(name, command).args(args.clone()).env(
+                        env.iter()
+                            .map(|(name, value)| EnvVariable::new(name, value))
+                            .collect(),
+                    ),
+                )),
+                McpServer::Http { name, .. } if !http_supported => {
+                    eprintln!("[mcp] skipping {name}: the agent does not support HTTP MCP servers");
+                    None
+                }
+                McpServer::Http { name, url, headers } => Some(AcpMcpServer::Http(
+                    McpServerHttp::new(name, url.as_str()).headers(
+                        headers
+                            .iter()
+                            .map(|(name, value)| HttpHeader::new(name, value))
+                            .collect(),
+                    ),
+                )),
+            })
+            .collect()
+    }
+
+    async fn setup_workspace(&self, task: &Task, workspace: &Path) -> Result<PathBuf> {
+        if workspace.exists() {
+            // Left behind by a previous run on this task, e.g. before the agent was restarted.
+            if current_branch(workspace).await.as_deref() == Some(task.git_branch.as_str()) {
+                eprintln!("[session] reusing workspace {}", workspace.display());
+                return Ok(workspace.to_path_buf());
+            }
+            anyhow::bail!("workspace path already exists: {}", workspace.display());
         }
-        if let Some(parent) = self.workspace_path.parent() {
+        if let Some(parent) = workspace.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
 
@@ -121,20 +487,20 @@ impl Agent {
             .set_password(Some(self.api_token.as_str()))
             .map_err(|_| anyhow::anyhow!("failed to set git URL password"))?;
 
-        self.run_git(&[
+        run_git(&[
             "clone",
             "--branch",
             task.git_branch.as_str(),
             git_url.as_str(),
-            self.workspace_path
+            workspace
                 .to_str()
                 .context("workspace path is not valid UTF-8")?,
         ])
         .await?;
 
-        self.run_git(&[
+        run_git(&[
             "-C",
-            self.workspace_path
+            workspace
                 .to_str()
                 .context("workspace path is not valid UTF-8")?,
             "config",
@@ -143,9 +509,9 @@ impl Agent {
         ])
         .await?;
 
-        self.run_git(&[
+        run_git(&[
             "-C",
-            self.workspace_path
+            workspace
                 .to_str()
                 .context("workspace path is not valid UTF-8")?,
             "config",
@@ -154,50 +520,17 @@ impl Agent {
         ])
         .await?;
 
-        Ok(self.workspace_path.clone())
-    }
-
-    async fn commit_and_push(&self, task: &Task) -> Result<()> {
-        let workspace = self
-            .workspace_path
-            .to_str()
-            .context("workspace path is not valid UTF-8")?;
-
-        self.run_git(&["-C", workspace, "add", "-A"]).await?;
-        self.run_git(&[
-            "-C",
-            workspace,
-            "commit",
-            "--allow-empty",
-            "-m",
-            "Complete task",
-        ])
-        .await?;
-        self.run_git(&["-C", workspace, "push", "origin", task.git_branch.as_str()])
-            .await?;
-        Ok(())
-    }
-
-    async fn run_git(&self, args: &[&str]) -> Result<()> {
-        let output = TokioCommand::new("git").args(args).output().await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            anyhow::bail!(
-                "git command failed (status={}): {}{}",
-                output.status,
-                stdout,
-                stderr
-            );
-        }
-        Ok(())
+        Ok(workspace.to_path_buf())
     }
 
     async fn spawn_acp(
         &self,
         workspace: &Path,
+        policy: PermissionPolicy,
+        checkpoints: Option<Repository>,
     ) -> Result<(
         ClientSideConnection,
+        ACPClient,
         tokio::process::Child,
         JoinHandle<AcpResult<()>>,
     )> {
@@ -214,14 +547,51 @@ impl Agent {
             .take()
             .context("ACP agent stdout unavailable")?;
 
-        let client = ACPClient::new(workspace.to_path_buf());
-        let (connection, io_task) =
-            ClientSideConnection::new(client, stdin.compat_write(), stdout.compat(), |fut| {
+        let sandbox = Sandbox::new(workspace)
+            .context("failed to resolve workspace path")?
+            .readable_paths(self.readable_paths.iter().cloned());
+        let mut client =
+            ACPClient::new(sandbox, self.api.clone(), policy).output_format(self.output_format);
+        if let Some(repository) = checkpoints {
+            client = client.checkpoints(repository);
+        }
+        let (connection, io_task) = ClientSideConnection::new(
+            client.clone(),
+            stdin.compat_write(),
+            stdout.compat(),
+            |fut| {
                 tokio::task::spawn_local(fut);
-            });
+            },
+        );
         let io_handle = tokio::task::spawn_local(io_task);
 
-        Ok((connection, child, io_handle))
+        Ok((connection, client, child, io_handle))
+    }
+}
+
+/// The branch checked out in a workspace, if it is a git repository.
+async fn current_branch(workspace: &Path) -> Option<String> {
+    if !workspace.join(".git").exists() {
+        return None;
+    }
+    let output = run_git(&[
+        "-C",
+        workspace.to_str()?,
+        "rev-parse",
+        "--abbrev-ref",
+        "HEAD",
+    ])
+    .await
+    .ok()?;
+    Some(output.trim().to_owned())
+}
+
+async fn remove_workspace(workspace: &Path) -> Result<()> {
+    match tokio::fs::remove_dir_all(workspace).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("failed to remove workspace {}", workspace.display()))
+        }
+        _ => Ok(()),
     }
 }
 
@@ -230,4 +600,86 @@ pub struct RunOutcome {
     pub workspace: PathBuf,
     pub session_id: SessionId,
     pub prompt_response: agent_client_protocol::PromptResponse,
+    /// What the agent said in its last turn.
+    pub final_message: String,
+    /// Whether the task was cancelled on the runtime while the agent was working on it.
+    pub cancelled: bool,
+}
+
+impl RunOutcome {
+    /// Whether the agent completed its task, judging by why it stopped.
+    pub fn task_result(&self) -> std::result::Result<TaskComplete, TaskFailure> {
+        let (reason, summary) = match self.prompt_response.stop_reason {
+            _ if self.cancelled => (
+                TaskFailureReason::Cancelled,
+                "The task was cancelled. The work so far has been committed.",
+            ),
+            StopReason::EndTurn => {
+                let description = if self.final_message.trim().is_empty() {
+                    format!("Completed task via ACP session {}", self.session_id)
+                } else {
+                    self.final_message.trim().to_owned()
+                };
+                return Ok(TaskComplete { description });
+            }
+            StopReason::MaxTokens => (
+                TaskFailureReason::ProblemSolving,
+                "The agent reached its token limit before completing the task.",
+            ),
+            StopReason::MaxTurnRequests => (
+                TaskFailureReason::ProblemSolving,
+                "The agent reached its request limit before completing the task.",
+            ),
+            StopReason::Refusal => (
+                TaskFailureReason::TaskIssues,
+                "The agent refused to work on the task.",
+            ),
+            StopReason::Cancelled => (
+                TaskFailureReason::TechnicalIssues,
+                "The agent's turn was cancelled.",
+            ),
+            _ => (
+                TaskFailureReason::TechnicalIssues,
+                "The agent stopped for an unknown reason.",
+            ),
+        };
+        let description = if self.final_message.trim().is_empty() {
+            summary.to_owned()
+        } else {
+            format!("{summary}\n\n{}", self.final_message.trim())
+        };
+        Err(TaskFailure {
+            reason: Some(reason),
+            description,
+        })
+    }
+}
+
+/// An error caused by the task rather than by the agent or the infrastructure,
+/// e.g. a request for a mode the agent does not offer.
+#[derive(Debug)]
+struct TaskIssue(String);
+
+impl std::fmt::Display for TaskIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TaskIssue {}
+
+/// Why running a task failed with an error. Invalid parameters of ACP requests stem from the
+/// task, e.g. a mode the agent rejects; everything else is a technical issue.
+fn failure_reason(err: &anyhow::Error) -> TaskFailureReason {
+    let caused_by_task = err.chain().any(|cause| {
+        cause.is::<TaskIssue>()
+            || cause
+                .downcast_ref::<agent_client_protocol::Error>()
+                .is_some_and(|err| err.code == ErrorCode::InvalidParams)
+    });
+    if caused_by_task {
+        TaskFailureReason::TaskIssues
+    } else {
+        TaskFailureReason::TechnicalIssues
+    }
 }
@@ -0,0 +1,349 @@
+//! Tests for running a session against a fake ACP agent process.
+#![cfg(test)]
+
+use std::sync::{Arc, Mutex};
+
+use agent_api::RetryPolicy;
+use agent_api::types::info::{API_VERSION, Capabilities, RuntimeInfo};
+use agent_api::types::session::SessionState;
+use agent_api::types::task::{Task, TaskFailureReason, TaskStatus};
+use agent_client_protocol::StopReason;
+use axum::routing::get;
+use axum::{Json, Router};
+use tempfile::TempDir;
+use tokio::process::Command as TokioCommand;
+use tokio::task::LocalSet;
+
+use super::{Agent, RunOutcome, TaskIssue, failure_reason};
+use crate::AgentConfig;
+
+/// A minimal ACP agent that answers every prompt with the message in `$MESSAGE` and
+/// the stop reason in `$STOP_REASON`. It advertises the capabilities in `$CAPABILITIES`.
+/// Only the session `$LOADABLE_SESSION` can be loaded, and a resumed session answers with
+/// "Resumed".
+const FAKE_AGENT: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":1,"agentCapabilities":{%s}}}\n' "$id" "$CAPABILITIES" ;;
+    *'"method":"session/new"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"sessionId":"fake-session"}}\n' "$id" ;;
+    *'"method":"session/load"'*)
+      case "$line" in
+        *"\"sessionId\":\"$LOADABLE_SESSION\""*)
+          printf '{"jsonrpc":"2.0","id":%s,"result":{}}\n' "$id" ;;
+        *)
+          printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32002,"message":"Resource not found"}}\n' "$id" ;;
+      esac ;;
+    *'"method":"session/prompt"'*)
+      text="$MESSAGE"
+      case "$line" in *'has been resumed'*) text="Resumed" ;; esac
+      if [ -n "$text" ]; then
+        printf '{"jsonrpc":"2.0","method":"session/update","params":{"sessionId":"fake-session","update":{"sessionUpdate":"agent_message_chunk","content":{"type":"text","text":"%s"}}}}\n' "$text"
+      fi
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"stopReason":"%s"}}\n' "$id" "$STOP_REASON" ;;
+  esac
+done
+"#;
+
+fn agent(
+    workspace: &TempDir,
+    script: &'static str,
+    envs: Vec<(&'static str, &'static str)>,
+) -> Agent {
+    // Nothing listens here, so there are never any follow-up messages.
+    agent_with_runtime(workspace, script, envs, "http://127.0.0.1:9/api/")
+}
+
+fn agent_with_runtime(
+    workspace: &TempDir,
+    script: &'static str,
+    envs: Vec<(&'static str, &'static str)>,
+    api_base_url: &str,
+) -> Agent {
+    let config = AgentConfig::new(
+        move || {
+            let mut cmd = TokioCommand::new("sh");
+            cmd.args(["-c", script]).envs(envs.clone());
+            cmd
+        },
+        api_base_url.parse().unwrap(),
+        "token",
+        workspace.path(),
+    )
+    .api_retry_policy(RetryPolicy {
+        max_retries: 0,
+        ..RetryPolicy::default()
+    });
+    Agent::new(config)
+}
+
+fn task() -> Task {
+    Task {
+        status: TaskStatus::Running,
+        description: "Do the thing".to_owned(),
+        git_user_name: "minion".to_owned(),
+        git_user_email: "minion@localhost".to_owned(),
+        git_repo_url: "http://localhost/repo.git".parse().unwrap(),
+        git_branch: "main".to_owned(),
+        permission_policy: None,
+        mcp_servers: Vec::new(),
+        mode: None,
+        model: None,
+        attachments: Vec::new(),
+        references: Vec::new(),
+        acceptance_criteria: Vec::new(),
+    }
+}
+
+async fn run(stop_reason: &'static str, message: &'static str) -> RunOutcome {
+    let workspace = tempfile::tempdir().unwrap();
+    let agent = agent(
+        &workspace,
+        FAKE_AGENT,
+        vec![("STOP_REASON", stop_reason), ("MESSAGE", message)],
+    );
+    LocalSet::new()
+        .run_until(agent.run_prompt(task(), workspace.path().to_path_buf()))
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn end_turn_completes_with_final_message() {
+    let outcome = run("end_turn", "All done").await;
+    assert_eq!(outcome.prompt_response.stop_reason, StopReason::EndTurn);
+    let Ok(complete) = outcome.task_result() else {
+        panic!("expected the task to be completed");
+    };
+    assert_eq!(complete.description, "All done");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn end_turn_without_message() {
+    let outcome = run("end_turn", "").await;
+    let Ok(complete) = outcome.task_result() else {
+        panic!("expected the task to be completed");
+    };
+    assert!(complete.description.contains("fake-session"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn refusal_is_a_task_issue() {
+    let outcome = run("refusal", "I cannot do that").await;
+    let Err(failure) = outcome.task_result() else {
+        panic!("expected the task to fail");
+    };
+    assert!(matches!(
+        failure.reason,
+        Some(TaskFailureReason::TaskIssues)
+    ));
+    assert!(failure.description.contains("I cannot do that"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn max_tokens_is_a_problem_solving_failure() {
+    let outcome = run("max_tokens", "Still working").await;
+    let Err(failure) = outcome.task_result() else {
+        panic!("expected the task to fail");
+    };
+    assert!(matches!(
+        failure.reason,
+        Some(TaskFailureReason::ProblemSolving)
+    ));
+    assert!(failure.description.contains("Still working"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn cancelled_is_a_technical_issue() {
+    let outcome = run("cancelled", "").await;
+    let Err(failure) = outcome.task_result() else {
+        panic!("expected the task to fail");
+    };
+    assert!(matches!(
+        failure.reason,
+        Some(TaskFailureReason::TechnicalIssues)
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn crashing_agent_is_an_error() {
+    let workspace = tempfile::tempdir().unwrap();
+    let agent = agent(&workspace, "exit 1", Vec::new());
+    let outcome = LocalSet::new()
+        .run_until(agent.run_prompt(task(), workspace.path().to_path_buf()))
+        .await;
+    let Err(err) = outcome else {
+        panic!("expected the run to fail");
+    };
+    assert!(matches!(
+        failure_reason(&err),
+        TaskFailureReason::TechnicalIssues
+    ));
+}
+
+#[test]
+fn errors_caused_by_the_task_are_task_issues() {
+    let unavailable_mode = anyhow::Error::new(TaskIssue("no such mode".to_owned()));
+    assert!(matches!(
+        failure_reason(&unavailable_mode),
+        TaskFailureReason::TaskIssues
+    ));
+    let invalid_params =
+        anyhow::Error::new(agent_client_protocol::Error::invalid_params()).context("prompt");
+    assert!(matches!(
+        failure_reason(&invalid_params),
+        TaskFailureReason::TaskIssues
+    ));
+    let internal = anyhow::Error::new(agent_client_protocol::Error::internal_error());
+    assert!(matches!(
+        failure_reason(&internal),
+        TaskFailureReason::TechnicalIssues
+    ));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn starts_new_session_without_saved_session() {
+    let workspace = tempfile::tempdir().unwrap();
+    let agent = agent(
+        &workspace,
+        FAKE_AGENT,
+        vec![
+            ("STOP_REASON", "end_turn"),
+            ("MESSAGE", "Done"),
+            ("CAPABILITIES", r#""loadSession":true"#),
+        ],
+    );
+    let outcome = LocalSet::new()
+        .run_until(agent.run_prompt(task(), workspace.path().to_path_buf()))
+        .await
+        .unwrap();
+    assert_eq!(outcome.session_id.to_string(), "fake-session");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn unavailable_mode_is_an_error() {
+    let workspace = tempfile::tempdir().unwrap();
+    let agent = agent(
+        &workspace,
+        FAKE_AGENT,
+        vec![("STOP_REASON", "end_turn"), ("MESSAGE", "Done")],
+    );
+    let task = Task {
+        mode: Some("plan".to_owned()),
+        ..task()
+    };
+    let outcome = LocalSet::new()
+        .run_until(agent.run_prompt(task, workspace.path().to_path_buf()))
+        .await;
+    let Err(err) = outcome else {
+        panic!("expected the run to fail");
+    };
+    assert!(err.to_string().contains("plan"));
+    assert!(matches!(
+        failure_reason(&err),
+        TaskFailureReason::TaskIssues
+    ));
+}
+
+#[test]
+fn cancelled_run_is_a_cancelled_failure() {
+    let outcome = RunOutcome {
+        task: task(),
+        workspace: "/workspace".into(),
+        session_id: "fake-session".into(),
+        prompt_response: agent_client_protocol::PromptResponse::new(StopReason::Cancelled),
+        final_message: "Half done".to_owned(),
+        cancelled: true,
+    };
+    let Err(failure) = outcome.task_result() else {
+        panic!("expected the task to fail");
+    };
+    assert!(matches!(failure.reason, Some(TaskFailureReason::Cancelled)));
+    assert!(failure.description.contains("Half done"));
+}
+
+/// A runtime that supports sessions, with the given session saved. Returns its API base URL
+/// and the sessions that are saved later on.
+async fn session_runtime(saved: Option<&str>) -> (String, Arc<Mutex<Vec<SessionState>>>) {
+    let saved = saved.map(|session_id| SessionState {
+        session_id: session_id.to_owned(),
+    });
+    let sessions = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new()
+        .route(
+            "/api/agent/info",
+            get(|| async {
+                Json(RuntimeInfo {
+                    api_version: API_VERSION,
+                    runtime: "test".to_owned(),
+                    capabilities: Capabilities {
+                        session: true,
+                        ..Capabilities::default()
+                    },
+                })
+            }),
+        )
+        .route(
+            "/api/agent/session",
+            get(move || async move { Json(saved) }).put({
+                let sessions = sessions.clone();
+                move |Json(session): Json<SessionState>| async move {
+                    sessions.lock().unwrap().push(session);
+                }
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, sessions)
+}
+
+async fn run_with_saved_session(saved: Option<&str>) -> (RunOutcome, Vec<SessionState>) {
+    let workspace = tempfile::tempdir().unwrap();
+    let (url, sessions) = session_runtime(saved).await;
+    let agent = agent_with_runtime(
+        &workspace,
+        FAKE_AGENT,
+        vec![
+            ("STOP_REASON", "end_turn"),
+            ("MESSAGE", "Started over"),
+            ("CAPABILITIES", r#""loadSession":true"#),
+            ("LOADABLE_SESSION", "saved-session"),
+        ],
+        &url,
+    );
+    let outcome = LocalSet::new()
+        .run_until(agent.run_prompt(task(), workspace.path().to_path_buf()))
+        .await
+        .unwrap();
+    let sessions = sessions.lock().unwrap().clone();
+    (outcome, sessions)
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn resumes_saved_session() {
+    let (outcome, saved) = run_with_saved_session(Some("saved-session")).await;
+    assert_eq!(outcome.session_id.to_string(), "saved-session");
+    assert_eq!(outcome.final_message, "Resumed");
+    assert!(saved.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn starts_over_if_the_agent_lost_the_session() {
+    // E.g. the task continues in a new container, without the agent's history.
+    let (outcome, saved) = run_with_saved_session(Some("lost-session")).await;
+    assert_eq!(outcome.session_id.to_string(), "fake-session");
+    assert_eq!(outcome.final_message, "Started over");
+    let saved: Vec<_> = saved.iter().map(|s| s.session_id.as_str()).collect();
+    assert_eq!(saved, ["fake-session"]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn saves_new_session() {
+    let (outcome, saved) = run_with_saved_session(None).await;
+    assert_eq!(outcome.session_id.to_string(), "fake-session");
+    let saved: Vec<_> = saved.iter().map(|s| s.session_id.as_str()).collect();
+    assert_eq!(saved, ["fake-session"]);
+}
@@ -1,14 +1,30 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use agent_api::RetryPolicy;
+use agent_api::types::mcp::McpServer;
+use agent_api::types::permission::PermissionPolicy;
 use tokio::process::Command as TokioCommand;
 use url::Url;
 
+use crate::OutputFormat;
+
 pub struct AgentConfig {
     pub acp_command: Arc<dyn Fn() -> TokioCommand + Send + Sync>,
     pub api_base_url: Url,
     pub api_token: String,
     pub workspace_path: PathBuf,
+    /// Used unless the task comes with its own permission policy.
+    pub permission_policy: PermissionPolicy,
+    /// Paths outside of the workspace that the agent may read.
+    pub readable_paths: Vec<PathBuf>,
+    /// Commit and push after each completed plan entry of the agent.
+    pub checkpoints: bool,
+    /// MCP servers for every task, in addition to those declared by the task.
+    pub mcp_servers: Vec<McpServer>,
+    pub output_format: OutputFormat,
+    /// How requests to the runtime API are retried after transient failures.
+    pub api_retry_policy: RetryPolicy,
 }
 
 impl AgentConfig {
@@ -31,6 +47,42 @@ impl AgentConfig {
             api_base_url,
             api_token: api_token.into(),
             workspace_path,
+            permission_policy: PermissionPolicy::default(),
+            readable_paths: Vec::new(),
+            checkpoints: false,
+            mcp_servers: Vec::new(),
+            output_format: OutputFormat::default(),
+            api_retry_policy: RetryPolicy::default(),
         }
     }
+
+    pub fn permission_policy(mut self, policy: PermissionPolicy) -> Self {
+        self.permission_policy = policy;
+        self
+    }
+
+    pub fn readable_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.readable_paths = paths;
+        self
+    }
+
+    pub fn checkpoints(mut self, checkpoints: bool) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
+    pub fn mcp_servers(mut self, mcp_servers: Vec<McpServer>) -> Self {
+        self.mcp_servers = mcp_servers;
+        self
+    }
+
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn api_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.api_retry_policy = policy;
+        self
+    }
 }
@@ -0,0 +1,106 @@
+//! Committing and pushing the agent's work.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use tokio::process::Command as TokioCommand;
+
+mod tests;
+
+/// Maximum length of the subject line of generated commit messages.
+const MAX_SUBJECT_LEN: usize = 72;
+
+/// The agent's clone of the task repository.
+#[derive(Debug, Clone)]
+pub(crate) struct Repository {
+    path: PathBuf,
+    branch: String,
+}
+
+impl Repository {
+    pub(crate) fn new(path: PathBuf, branch: String) -> Self {
+        Self { path, branch }
+    }
+
+    /// Commit all changes. Returns `false` if there was nothing to commit.
+    pub(crate) async fn commit(&self, message: &str, allow_empty: bool) -> Result<bool> {
+        let path = self.path_str()?;
+        run_git(&["-C", path, "add", "-A"]).await?;
+        let status = run_git(&["-C", path, "status", "--porcelain"]).await?;
+        if status.trim().is_empty() && !allow_empty {
+            return Ok(false);
+        }
+        run_git(&["-C", path, "commit", "--allow-empty", "-m", message]).await?;
+        Ok(true)
+    }
+
+    pub(crate) async fn push(&self) -> Result<()> {
+        run_git(&["-C", self.path_str()?, "push", "origin", &self.branch]).await?;
+        Ok(())
+    }
+
+    /// Commit and push the current state of the work, if anything changed.
+    pub(crate) async fn checkpoint(&self, title: &str) -> Result<()> {
+        let message = format!("Checkpoint: {}", subject_line(title));
+        if self.commit(&message, false).await? {
+            self.push().await?;
+        }
+        Ok(())
+    }
+
+    fn path_str(&self) -> Result<&str> {
+        self.path
+            .to_str()
+            .context("workspace path is not valid UTF-8")
+    }
+}
+
+/// Build a commit message from the task description and the agent's summary of its work.
+pub(crate) fn commit_message(task_description: &str, summary: &str) -> String {
+    let subject = subject_line(task_description);
+    let summary = summary.trim();
+    let task_description = task_description.trim();
+
+    let mut message = subject.clone();
+    if !summary.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(summary);
+    }
+    // Keep the full task description unless the subject already says it all.
+    if task_description != subject {
+        message.push_str("\n\nTask:\n");
+        message.push_str(task_description);
+    }
+    message
+}
+
+/// First line of a text, shortened to fit a commit subject.
+fn subject_line(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("Complete task");
+    if line.chars().count() <= MAX_SUBJECT_LEN {
+        line.to_owned()
+    } else {
+        let shortened: String = line.chars().take(MAX_SUBJECT_LEN - 3).collect();
+        format!("{}...", shortened.trim_end())
+    }
+}
+
+/// Run git and return its standard output.
+pub(crate) async fn run_git(args: &[&str]) -> Result<String> {
+    let output = TokioCommand::new("git").args(args).output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        anyhow::bail!(
+            "git command failed (status={}): {}{}",
+            output.status,
+            stdout,
+            stderr
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
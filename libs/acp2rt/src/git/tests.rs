@@ -0,0 +1,53 @@
+//! Tests for the generated commit messages.
+#![cfg(test)]
+
+use super::{MAX_SUBJECT_LEN, commit_message, subject_line};
+
+#[test]
+fn subject_is_the_first_non_empty_line() {
+    assert_eq!(
+        subject_line("\n  Fix the build  \nDetails"),
+        "Fix the build"
+    );
+    assert_eq!(subject_line("  \n"), "Complete task");
+}
+
+#[test]
+fn long_subjects_are_shortened_by_characters() {
+    let exact = "ä".repeat(MAX_SUBJECT_LEN);
+    assert_eq!(subject_line(&exact), exact);
+
+    let long = "ä".repeat(MAX_SUBJECT_LEN + 10);
+    let subject = subject_line(&long);
+    assert_eq!(subject.chars().count(), MAX_SUBJECT_LEN);
+    assert!(subject.ends_with("ä..."));
+
+    // Whitespace before the ellipsis is dropped.
+    let words = format!("{} word", "a".repeat(MAX_SUBJECT_LEN - 4));
+    assert_eq!(
+        subject_line(&words),
+        format!("{}...", "a".repeat(MAX_SUBJECT_LEN - 4))
+    );
+}
+
+#[test]
+fn short_task_is_only_the_subject() {
+    assert_eq!(
+        commit_message("  Fix the build\n", "Updated the lock file."),
+        "Fix the build\n\nUpdated the lock file."
+    );
+    assert_eq!(commit_message("Fix the build", " "), "Fix the build");
+}
+
+#[test]
+fn longer_task_is_kept_in_full() {
+    let task = "Fix the build\n\nThe lock file is outdated.";
+    assert_eq!(
+        commit_message(task, "Done."),
+        "Fix the build\n\nDone.\n\nTask:\nFix the build\n\nThe lock file is outdated."
+    );
+
+    let long = "x".repeat(MAX_SUBJECT_LEN + 1);
+    let message = commit_message(&long, "");
+    assert!(message.ends_with(&format!("\n\nTask:\n{long}")));
+}
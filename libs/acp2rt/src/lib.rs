@@ -4,9 +4,17 @@
 mod acp_client;
 mod agent;
 mod config;
+mod git;
+mod permission;
+mod prompt;
+mod render;
+mod sandbox;
+mod terminal;
 
 pub type AcpResult<T> = agent_client_protocol::Result<T>;
 
 pub use acp_client::ACPClient;
 pub use agent::{Agent, RunOutcome};
 pub use config::AgentConfig;
+pub use render::OutputFormat;
+pub use sandbox::Sandbox;
@@ -5,13 +5,34 @@ use std::{env, path::PathBuf};
 use clap::Parser;
 use tokio::process::Command as TokioCommand;
 
-use acp2rt::{Agent, AgentConfig};
+use acp2rt::{Agent, AgentConfig, OutputFormat};
+use agent_api::types::mcp::McpServers;
+use agent_api::types::permission::PermissionPolicy;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    /// Where to clone the task repository. In worker mode, the directory for all workspaces
     #[arg(long)]
     workspace_path: PathBuf,
+    /// Keep polling for queued tasks instead of running a single task
+    #[arg(long = "loop")]
+    worker: bool,
+    /// TOML file with the policy for answering permission requests of the agent
+    #[arg(long)]
+    permission_policy: Option<PathBuf>,
+    /// Allow the agent to read this file or directory outside of the workspace (repeatable)
+    #[arg(long = "allow-read", value_name = "PATH")]
+    readable_paths: Vec<PathBuf>,
+    /// Commit and push the work in progress whenever the agent completes a plan entry
+    #[arg(long)]
+    checkpoints: bool,
+    /// TOML file declaring MCP servers (`[[mcp_servers]]`) to pass to the agent
+    #[arg(long)]
+    mcp_servers: Option<PathBuf>,
+    /// How to write the session updates of the agent to the console
+    #[arg(long, value_enum, default_value_t)]
+    output_format: OutputFormat,
     #[arg(required = true, trailing_var_arg = true)]
     command: Vec<OsString>,
 }
@@ -25,6 +46,16 @@ async fn main() -> anyhow::Result<()> {
     let api_base_url_parsed = url::Url::parse(&api_base_url)?;
     let api_token = env::var("MINION_API_TOKEN")?;
 
+    let permission_policy = match &args.permission_policy {
+        Some(path) => toml::from_str(&std::fs::read_to_string(path)?)?,
+        None => PermissionPolicy::default(),
+    };
+
+    let mcp_servers: McpServers = match &args.mcp_servers {
+        Some(path) => toml::from_str(&std::fs::read_to_string(path)?)?,
+        None => McpServers::default(),
+    };
+
     let config = AgentConfig::new(
         {
             let command = Arc::clone(&command);
@@ -39,9 +70,18 @@ async fn main() -> anyhow::Result<()> {
         api_base_url_parsed,
         api_token,
         args.workspace_path,
-    );
+    )
+    .permission_policy(permission_policy)
+    .readable_paths(args.readable_paths)
+    .checkpoints(args.checkpoints)
+    .mcp_servers(mcp_servers.mcp_servers)
+    .output_format(args.output_format);
 
     let agent = Agent::new(config);
-    let _ = agent.run_once().await?;
-    Ok(())
+    if args.worker {
+        agent.run_loop().await
+    } else {
+        let _ = agent.run_once().await?;
+        Ok(())
+    }
 }
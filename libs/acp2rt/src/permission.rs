@@ -0,0 +1,135 @@
+//! Answering permission requests of the agent according to a [`PermissionPolicy`].
+
+use agent_api::Client as AgentApiClient;
+use agent_api::types::permission::{PermissionDecision, PermissionPolicy};
+use agent_client_protocol::{
+    PermissionOption, PermissionOptionKind, RequestPermissionRequest, ToolKind,
+};
+
+mod tests;
+
+const ALLOW_ONCE: &[PermissionOptionKind] = &[
+    PermissionOptionKind::AllowOnce,
+    PermissionOptionKind::AllowAlways,
+];
+const ALLOW_ALWAYS: &[PermissionOptionKind] = &[
+    PermissionOptionKind::AllowAlways,
+    PermissionOptionKind::AllowOnce,
+];
+const REJECT: &[PermissionOptionKind] = &[
+    PermissionOptionKind::RejectOnce,
+    PermissionOptionKind::RejectAlways,
+];
+
+/// Choose one of the offered options, or `None` to cancel the request.
+/// Every decision is logged.
+pub(crate) async fn choose_option<'a>(
+    policy: &PermissionPolicy,
+    api: &AgentApiClient,
+    args: &'a RequestPermissionRequest,
+) -> Option<&'a PermissionOption> {
+    let title = args.tool_call.fields.title.as_deref().unwrap_or_default();
+    let kind = tool_kind_name(args.tool_call.fields.kind.unwrap_or_default());
+    let (decision, rule) = policy.decide(kind, title);
+
+    let option = match decision {
+        PermissionDecision::AllowOnce => option_by_kind(&args.options, ALLOW_ONCE),
+        PermissionDecision::AllowAlways => option_by_kind(&args.options, ALLOW_ALWAYS),
+        PermissionDecision::Reject => option_by_kind(&args.options, REJECT),
+        PermissionDecision::Ask => ask_user(api, args).await,
+    };
+
+    let source = match rule {
+        Some(i) => format!("rule {}", i + 1),
+        None => "default".to_owned(),
+    };
+    let chosen = option.map_or("cancelled", |option| option.name.as_str());
+    eprintln!("[permission] {title:?} ({kind}): {decision:?} by {source} -> {chosen}");
+
+    option
+}
+
+/// Ask the user via the runtime.
+///
+/// Without a usable answer (e.g. inquiries are not answered during this run, or the answer
+/// names no option) the request is rejected, or cancelled if rejecting is not an option.
+async fn ask_user<'a>(
+    api: &AgentApiClient,
+    args: &'a RequestPermissionRequest,
+) -> Option<&'a PermissionOption> {
+    match api.inquire(permission_question(args)).await {
+        Ok(answer) => match match_permission_answer(&args.options, &answer) {
+            Some(option) => return Some(option),
+            None => eprintln!("[permission] could not interpret answer {answer:?}, rejecting"),
+        },
+        Err(err) => eprintln!("[permission] failed to ask for permission: {err}, rejecting"),
+    }
+    option_by_kind(&args.options, REJECT)
+}
+
+/// The first option of the most preferred kind that is offered.
+fn option_by_kind<'a>(
+    options: &'a [PermissionOption],
+    kinds: &[PermissionOptionKind],
+) -> Option<&'a PermissionOption> {
+    kinds
+        .iter()
+        .find_map(|kind| options.iter().find(|option| option.kind == *kind))
+}
+
+/// Phrase a permission request as a question for the user.
+fn permission_question(args: &RequestPermissionRequest) -> String {
+    let title = args
+        .tool_call
+        .fields
+        .title
+        .as_deref()
+        .unwrap_or("an unnamed tool call");
+    let mut question = format!("The agent requests permission to run {title}.\n");
+    if let Some(raw_input) = &args.tool_call.fields.raw_input {
+        question.push_str(&format!("Input: {raw_input}\n"));
+    }
+    question.push_str("Answer with one of the following options:\n");
+    for (i, option) in args.options.iter().enumerate() {
+        question.push_str(&format!("  {}. {}\n", i + 1, option.name));
+    }
+    question
+}
+
+/// Find the permission option the user chose, by number, name or a plain yes/no.
+fn match_permission_answer<'a>(
+    options: &'a [PermissionOption],
+    answer: &str,
+) -> Option<&'a PermissionOption> {
+    let answer = answer.trim();
+    if let Ok(n) = answer.trim_end_matches('.').parse::<usize>() {
+        return n.checked_sub(1).and_then(|i| options.get(i));
+    }
+    if let Some(option) = options.iter().find(|option| {
+        option.name.eq_ignore_ascii_case(answer) || option.option_id.0.as_ref() == answer
+    }) {
+        return Some(option);
+    }
+
+    match answer.to_lowercase().as_str() {
+        "y" | "yes" | "allow" | "ok" => option_by_kind(options, ALLOW_ONCE),
+        "n" | "no" | "deny" | "reject" => option_by_kind(options, REJECT),
+        _ => None,
+    }
+}
+
+/// Name of a tool kind as used in the ACP schema and in permission rules.
+pub(crate) fn tool_kind_name(kind: ToolKind) -> &'static str {
+    match kind {
+        ToolKind::Read => "read",
+        ToolKind::Edit => "edit",
+        ToolKind::Delete => "delete",
+        ToolKind::Move => "move",
+        ToolKind::Search => "search",
+        ToolKind::Execute => "execute",
+        ToolKind::Think => "think",
+        ToolKind::Fetch => "fetch",
+        ToolKind::SwitchMode => "switch_mode",
+        _ => "other",
+    }
+}
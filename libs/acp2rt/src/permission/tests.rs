@@ -0,0 +1,165 @@
+//! Tests for choosing permission options according to the policy and the user's answer.
+#![cfg(test)]
+
+use agent_api::Client as AgentApiClient;
+use agent_api::types::permission::{PermissionDecision, PermissionPolicy};
+use agent_client_protocol::{
+    PermissionOption, PermissionOptionKind, RequestPermissionRequest, ToolCallUpdate,
+    ToolCallUpdateFields, ToolKind,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use super::{choose_option, match_permission_answer};
+
+fn options() -> Vec<PermissionOption> {
+    vec![
+        PermissionOption::new("allow", "Allow", PermissionOptionKind::AllowOnce),
+        PermissionOption::new("always", "Always allow", PermissionOptionKind::AllowAlways),
+        PermissionOption::new("reject", "Reject", PermissionOptionKind::RejectOnce),
+    ]
+}
+
+fn chosen(option: Option<&PermissionOption>) -> Option<&str> {
+    option.map(|option| option.option_id.0.as_ref())
+}
+
+#[test]
+fn answers_name_options_by_number_name_or_id() {
+    let options = options();
+    assert_eq!(
+        chosen(match_permission_answer(&options, "2")),
+        Some("always")
+    );
+    assert_eq!(
+        chosen(match_permission_answer(&options, " 3. ")),
+        Some("reject")
+    );
+    assert_eq!(
+        chosen(match_permission_answer(&options, "always allow")),
+        Some("always")
+    );
+    assert_eq!(
+        chosen(match_permission_answer(&options, "reject")),
+        Some("reject")
+    );
+    assert_eq!(
+        chosen(match_permission_answer(&options, "Yes")),
+        Some("allow")
+    );
+    assert_eq!(
+        chosen(match_permission_answer(&options, "n")),
+        Some("reject")
+    );
+}
+
+#[test]
+fn unparseable_answers_match_no_option() {
+    let options = options();
+    assert_eq!(chosen(match_permission_answer(&options, "0")), None);
+    assert_eq!(chosen(match_permission_answer(&options, "4")), None);
+    assert_eq!(
+        chosen(match_permission_answer(&options, "sure, why not")),
+        None
+    );
+    assert_eq!(
+        chosen(match_permission_answer(
+            &options,
+            "No answer is available. Proceed as you see fit."
+        )),
+        None
+    );
+}
+
+/// A runtime that answers a single inquiry with `answer`.
+async fn runtime_answering(answer: &'static str) -> AgentApiClient {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = [0; 4096];
+        let _ = stream.read(&mut request).await;
+        let body = serde_json::to_string(answer).unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+    });
+    AgentApiClient::new(url.parse().unwrap(), "token".to_owned())
+}
+
+fn request(options: Vec<PermissionOption>) -> RequestPermissionRequest {
+    let fields = ToolCallUpdateFields::new()
+        .kind(ToolKind::Execute)
+        .title("rm -rf /".to_owned());
+    RequestPermissionRequest::new("session", ToolCallUpdate::new("call", fields), options)
+}
+
+#[tokio::test]
+async fn unusable_answers_reject() {
+    let policy = PermissionPolicy::default();
+    let args = request(options());
+
+    let api = runtime_answering("No answer is available. Proceed as you see fit.").await;
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &args).await),
+        Some("reject")
+    );
+
+    let api = runtime_answering("1").await;
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &args).await),
+        Some("allow")
+    );
+}
+
+#[tokio::test]
+async fn failed_inquiries_reject_or_cancel() {
+    let policy = PermissionPolicy::default();
+    // Nothing listens on the port once the listener is dropped.
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    };
+    let api = AgentApiClient::new(url.parse().unwrap(), "token".to_owned());
+
+    let args = request(options());
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &args).await),
+        Some("reject")
+    );
+
+    let allow_only = request(options().into_iter().take(2).collect());
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &allow_only).await),
+        None
+    );
+}
+
+#[tokio::test]
+async fn policy_decisions_do_not_ask() {
+    let policy = PermissionPolicy {
+        rules: Vec::new(),
+        default: PermissionDecision::AllowAlways,
+    };
+    // Asking would fail, so any answer comes from the policy.
+    let api = AgentApiClient::new("http://127.0.0.1:1/".parse().unwrap(), "token".to_owned());
+    let args = request(options());
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &args).await),
+        Some("always")
+    );
+}
+
+#[tokio::test]
+async fn requests_are_not_asked_without_interactive_inquiries() {
+    // The policy of an auto-answer run. Asking the runtime would allow the request.
+    let policy = PermissionPolicy::default().without_asking();
+    let api = runtime_answering("1").await;
+    let args = request(options());
+    assert_eq!(
+        chosen(choose_option(&policy, &api, &args).await),
+        Some("reject")
+    );
+}
@@ -0,0 +1,87 @@
+//! The first prompt of a task: its description, structured inputs and attachments.
+
+use agent_api::types::task::{Attachment, AttachmentContent, Task};
+use agent_client_protocol::{
+    BlobResourceContents, ContentBlock, EmbeddedResource, EmbeddedResourceResource, ImageContent,
+    PromptCapabilities, TextContent, TextResourceContents,
+};
+use url::Url;
+
+mod tests;
+
+/// Build the prompt for `task`. Attachments are passed as images or embedded resources if the
+/// agent accepts them, and otherwise inlined as text where possible.
+pub(crate) fn task_prompt(task: &Task, capabilities: &PromptCapabilities) -> Vec<ContentBlock> {
+    let mut text = task.description.clone();
+    if !task.acceptance_criteria.is_empty() {
+        text.push_str("\n\nAcceptance criteria:");
+        for criterion in &task.acceptance_criteria {
+            text.push_str(&format!("\n- {criterion}"));
+        }
+    }
+    if !task.references.is_empty() {
+        text.push_str("\n\nRelevant files in the repository:");
+        for reference in &task.references {
+            text.push_str(&format!("\n- {reference}"));
+        }
+    }
+    if !task.attachments.is_empty() {
+        text.push_str("\n\nThe user attached these files:");
+        for attachment in &task.attachments {
+            text.push_str(&format!(
+                "\n- {} ({})",
+                attachment.name, attachment.mime_type
+            ));
+        }
+    }
+
+    let mut prompt = vec![ContentBlock::Text(TextContent::new(text))];
+    prompt.extend(
+        task.attachments
+            .iter()
+            .map(|attachment| attachment_block(attachment, capabilities)),
+    );
+    prompt
+}
+
+fn attachment_block(attachment: &Attachment, capabilities: &PromptCapabilities) -> ContentBlock {
+    let uri = attachment_uri(&attachment.name);
+    match &attachment.content {
+        AttachmentContent::Base64 { data }
+            if capabilities.image && attachment.mime_type.starts_with("image/") =>
+        {
+            ContentBlock::Image(ImageContent::new(data, &attachment.mime_type).uri(uri))
+        }
+        AttachmentContent::Text { text } if capabilities.embedded_context => {
+            ContentBlock::Resource(EmbeddedResource::new(
+                EmbeddedResourceResource::TextResourceContents(
+                    TextResourceContents::new(text, uri).mime_type(&attachment.mime_type),
+                ),
+            ))
+        }
+        AttachmentContent::Base64 { data } if capabilities.embedded_context => {
+            ContentBlock::Resource(EmbeddedResource::new(
+                EmbeddedResourceResource::BlobResourceContents(
+                    BlobResourceContents::new(data, uri).mime_type(&attachment.mime_type),
+                ),
+            ))
+        }
+        AttachmentContent::Text { text } => ContentBlock::Text(TextContent::new(format!(
+            "Attached file {}:\n\n```\n{text}\n```",
+            attachment.name
+        ))),
+        AttachmentContent::Base64 { .. } => ContentBlock::Text(TextContent::new(format!(
+            "Attached file {} could not be passed on, since the agent does not accept {} files.",
+            attachment.name, attachment.mime_type
+        ))),
+    }
+}
+
+/// Attachments are not in the workspace, so they are identified by their name only.
+fn attachment_uri(name: &str) -> String {
+    let mut uri = Url::parse("attachment:///").expect("Failed to parse URL");
+    uri.path_segments_mut()
+        .expect("Attachment URLs have a path")
+        .push(name);
+    uri.to_string()
+}
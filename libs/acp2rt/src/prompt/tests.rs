@@ -0,0 +1,100 @@
+#![cfg(test)]
+
+use agent_api::types::task::{
+    Attachment, AttachmentContent, FileReference, LineRange, Task, TaskStatus,
+};
+use agent_client_protocol::{ContentBlock, EmbeddedResourceResource, PromptCapabilities};
+
+use super::task_prompt;
+
+fn task(attachments: Vec<Attachment>) -> Task {
+    Task {
+        status: TaskStatus::Running,
+        description: "Fix the crash".to_owned(),
+        git_user_name: "minion".to_owned(),
+        git_user_email: "minion@localhost".to_owned(),
+        git_repo_url: "http://localhost/repo.git".parse().unwrap(),
+        git_branch: "main".to_owned(),
+        permission_policy: None,
+        mcp_servers: Vec::new(),
+        mode: None,
+        model: None,
+        attachments,
+        references: vec![FileReference {
+            path: "src/main.rs".to_owned(),
+            lines: Some(LineRange { start: 10, end: 20 }),
+        }],
+        acceptance_criteria: vec!["cargo test passes".to_owned()],
+    }
+}
+
+fn log() -> Attachment {
+    Attachment {
+        name: "crash report.log".to_owned(),
+        mime_type: "text/plain".to_owned(),
+        content: AttachmentContent::Text {
+            text: "panicked at src/main.rs:12".to_owned(),
+        },
+    }
+}
+
+fn screenshot() -> Attachment {
+    Attachment {
+        name: "screenshot.png".to_owned(),
+        mime_type: "image/png".to_owned(),
+        content: AttachmentContent::Base64 {
+            data: "iVBORw0KGgo=".to_owned(),
+        },
+    }
+}
+
+fn text(block: &ContentBlock) -> &str {
+    match block {
+        ContentBlock::Text(text) => &text.text,
+        other => panic!("expected text, got {other:?}"),
+    }
+}
+
+#[test]
+fn structured_inputs_are_listed_after_the_description() {
+    let prompt = task_prompt(&task(vec![log()]), &PromptCapabilities::new());
+    assert_eq!(
+        text(&prompt[0]),
+        "Fix the crash\n\n\
+         Acceptance criteria:\n- cargo test passes\n\n\
+         Relevant files in the repository:\n- src/main.rs:10-20\n\n\
+         The user attached these files:\n- crash report.log (text/plain)"
+    );
+}
+
+#[test]
+fn attachments_are_embedded_if_the_agent_accepts_them() {
+    let capabilities = PromptCapabilities::new().image(true).embedded_context(true);
+    let prompt = task_prompt(&task(vec![log(), screenshot()]), &capabilities);
+    assert_eq!(prompt.len(), 3);
+
+    let ContentBlock::Resource(resource) = &prompt[1] else {
+        panic!("expected resource, got {:?}", prompt[1]);
+    };
+    let EmbeddedResourceResource::TextResourceContents(contents) = &resource.resource else {
+        panic!("expected text resource");
+    };
+    assert_eq!(contents.uri, "attachment:///crash%20report.log");
+    assert_eq!(contents.text, "panicked at src/main.rs:12");
+
+    let ContentBlock::Image(image) = &prompt[2] else {
+        panic!("expected image, got {:?}", prompt[2]);
+    };
+    assert_eq!(image.mime_type, "image/png");
+    assert_eq!(image.data, "iVBORw0KGgo=");
+}
+
+#[test]
+fn attachments_fall_back_to_text() {
+    let prompt = task_prompt(&task(vec![log(), screenshot()]), &PromptCapabilities::new());
+    assert_eq!(
+        text(&prompt[1]),
+        "Attached file crash report.log:\n\n```\npanicked at src/main.rs:12\n```"
+    );
+    assert!(text(&prompt[2]).contains("does not accept image/png files"));
+}
@@ -0,0 +1,30 @@
+//! Line-based unified diffs for file edits of the agent.
+
+use std::time::Duration;
+
+use similar::TextDiff;
+
+/// Lines of unchanged context around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Give up on finding a minimal diff after this long and replace the remaining changed regions
+/// as a whole. Keeps rendering of large rewrites cheap.
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Render the change of a file from `old` to `new` as a unified diff.
+///
+/// Without an old text, the file is shown as newly created.
+/// Returns an empty string if nothing changed.
+pub(crate) fn unified_diff(path: &str, old: Option<&str>, new: &str) -> String {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old.unwrap_or_default(), new);
+    let old_header = match old {
+        Some(_) => format!("a/{path}"),
+        None => "/dev/null".to_owned(),
+    };
+    diff.unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old_header, &format!("b/{path}"))
+        .to_string()
+}
@@ -0,0 +1,248 @@
+//! Rendering of ACP session updates on the console of the runtime.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use agent_client_protocol::*;
+
+use crate::permission::tool_kind_name;
+use crate::terminal::Terminals;
+
+mod diff;
+mod tests;
+
+/// How session updates of the agent are written to the console.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable: messages on stdout, tool calls, diffs and plans on stderr.
+    #[default]
+    Text,
+    /// One JSON-encoded session notification per line on stdout.
+    JsonLines,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Renderer {
+    format: OutputFormat,
+    /// Latest known title and status of each tool call, to render status transitions.
+    tool_calls: Mutex<HashMap<ToolCallId, ToolCallState>>,
+    /// Terminals whose output has been rendered already.
+    rendered_terminals: Mutex<HashSet<TerminalId>>,
+}
+
+#[derive(Debug)]
+struct ToolCallState {
+    title: String,
+    status: ToolCallStatus,
+}
+
+impl Renderer {
+    pub(crate) fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn render(&self, notification: &SessionNotification, terminals: &Terminals) {
+        match self.format {
+            OutputFormat::Text => self.render_text(&notification.update, terminals),
+            OutputFormat::JsonLines => match serde_json::to_string(notification) {
+                Ok(line) => println!("{line}"),
+                Err(err) => eprintln!("[render] failed to encode session update: {err}"),
+            },
+        }
+    }
+
+    fn render_text(&self, update: &SessionUpdate, terminals: &Terminals) {
+        match update {
+            SessionUpdate::UserMessageChunk(chunk) | SessionUpdate::AgentMessageChunk(chunk) => {
+                print!("{}", describe_content(&chunk.content));
+                let _ = io::stdout().flush();
+            }
+            SessionUpdate::AgentThoughtChunk(chunk) => {
+                eprint!("{}", describe_content(&chunk.content));
+                let _ = io::stderr().flush();
+            }
+            SessionUpdate::ToolCall(tool_call) => {
+                self.tool_calls.lock().unwrap().insert(
+                    tool_call.tool_call_id.clone(),
+                    ToolCallState {
+                        title: tool_call.title.clone(),
+                        status: tool_call.status,
+                    },
+                );
+                eprintln!(
+                    "\n[tool call] {} ({}, {})",
+                    tool_call.title,
+                    tool_kind_name(tool_call.kind),
+                    status_name(tool_call.status)
+                );
+                render_locations(&tool_call.locations);
+                self.render_tool_call_content(&tool_call.content, tool_call.status, terminals);
+            }
+            SessionUpdate::ToolCallUpdate(update) => {
+                self.render_tool_call_update(update, terminals)
+            }
+            SessionUpdate::Plan(plan) => eprint!("{}", render_plan(plan)),
+            SessionUpdate::CurrentModeUpdate(update) => {
+                eprintln!("\n[mode] {}", update.current_mode_id);
+            }
+            _ => (),
+        }
+    }
+
+    fn render_tool_call_update(&self, update: &ToolCallUpdate, terminals: &Terminals) {
+        let fields = &update.fields;
+        let (title, transition, status) = {
+            let mut tool_calls = self.tool_calls.lock().unwrap();
+            let state = tool_calls
+                .entry(update.tool_call_id.clone())
+                .or_insert_with(|| ToolCallState {
+                    title: update.tool_call_id.to_string(),
+                    status: ToolCallStatus::Pending,
+                });
+            if let Some(title) = &fields.title {
+                state.title = title.clone();
+            }
+            let transition = fields
+                .status
+                .filter(|status| *status != state.status)
+                .map(|status| (state.status, status));
+            if let Some(status) = fields.status {
+                state.status = status;
+            }
+            (state.title.clone(), transition, state.status)
+        };
+
+        if let Some((from, to)) = transition {
+            eprintln!(
+                "[tool call] {title}: {} -> {}",
+                status_name(from),
+                status_name(to)
+            );
+        }
+        if let Some(locations) = &fields.locations {
+            render_locations(locations);
+        }
+        if let Some(content) = &fields.content {
+            self.render_tool_call_content(content, status, terminals);
+        }
+    }
+
+    fn render_tool_call_content(
+        &self,
+        content: &[ToolCallContent],
+        status: ToolCallStatus,
+        terminals: &Terminals,
+    ) {
+        for item in content {
+            match item {
+                ToolCallContent::Content(content) => {
+                    eprintln!("{}", indent(&describe_content(&content.content)));
+                }
+                ToolCallContent::Diff(diff) => {
+                    let path = diff.path.display().to_string();
+                    eprint!(
+                        "{}",
+                        diff::unified_diff(&path, diff.old_text.as_deref(), &diff.new_text)
+                    );
+                }
+                ToolCallContent::Terminal(terminal) => {
+                    self.render_terminal(&terminal.terminal_id, status, terminals);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Render the output of a terminal once its command has exited or the tool call is done.
+    fn render_terminal(&self, id: &TerminalId, status: ToolCallStatus, terminals: &Terminals) {
+        let Ok((output, truncated, exit_status)) = terminals.output(id) else {
+            return;
+        };
+        let done = matches!(status, ToolCallStatus::Completed | ToolCallStatus::Failed);
+        if !done && exit_status.is_none() {
+            return;
+        }
+        if !self.rendered_terminals.lock().unwrap().insert(id.clone()) {
+            return;
+        }
+        let exit = match exit_status {
+            Some(TerminalExitStatus {
+                exit_code: Some(code),
+                ..
+            }) => format!("exited with code {code}"),
+            Some(TerminalExitStatus {
+                signal: Some(signal),
+                ..
+            }) => format!("killed by {signal}"),
+            _ => "still running".to_owned(),
+        };
+        let truncated = if truncated { ", output truncated" } else { "" };
+        eprintln!("[terminal {id}] {exit}{truncated}");
+        if !output.is_empty() {
+            eprintln!("{}", indent(output.trim_end()));
+        }
+    }
+}
+
+fn render_locations(locations: &[ToolCallLocation]) {
+    for location in locations {
+        match location.line {
+            Some(line) => eprintln!("  at {}:{line}", location.path.display()),
+            None => eprintln!("  at {}", location.path.display()),
+        }
+    }
+}
+
+fn render_plan(plan: &Plan) -> String {
+    let mut out = String::from("\n[plan]\n");
+    for entry in &plan.entries {
+        let marker = match entry.status {
+            PlanEntryStatus::Completed => "[x]",
+            PlanEntryStatus::InProgress => "[>]",
+            _ => "[ ]",
+        };
+        out.push_str(&format!("  {marker} {}\n", entry.content));
+    }
+    out
+}
+
+/// Text of a content block, or a short description of non-text content.
+fn describe_content(content: &ContentBlock) -> String {
+    match content {
+        ContentBlock::Text(text) => text.text.clone(),
+        ContentBlock::Image(image) => format!("[image: {}]", image.mime_type),
+        ContentBlock::Audio(audio) => format!("[audio: {}]", audio.mime_type),
+        ContentBlock::ResourceLink(link) => format!("[resource: {}]", link.uri),
+        ContentBlock::Resource(resource) => match &resource.resource {
+            EmbeddedResourceResource::TextResourceContents(contents) => {
+                format!("[resource: {}]", contents.uri)
+            }
+            EmbeddedResourceResource::BlobResourceContents(contents) => {
+                format!("[resource: {}]", contents.uri)
+            }
+            _ => "[resource]".to_owned(),
+        },
+        _ => "[unsupported content]".to_owned(),
+    }
+}
+
+fn status_name(status: ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Pending => "pending",
+        ToolCallStatus::InProgress => "in_progress",
+        ToolCallStatus::Completed => "completed",
+        ToolCallStatus::Failed => "failed",
+        _ => "unknown",
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
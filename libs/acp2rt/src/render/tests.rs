@@ -0,0 +1,118 @@
+#![cfg(test)]
+
+use agent_client_protocol::{Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus};
+
+use super::diff::unified_diff;
+use super::render_plan;
+
+#[test]
+fn diff_of_unchanged_text_is_empty() {
+    assert_eq!(unified_diff("a.txt", Some("one\ntwo\n"), "one\ntwo\n"), "");
+}
+
+#[test]
+fn diff_shows_changed_line_with_context() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+    assert_eq!(
+        unified_diff("src/lib.rs", Some(old), new),
+        "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+}
+
+#[test]
+fn diff_splits_distant_changes_into_hunks() {
+    let old: String = (1..=20).map(|i| format!("{i}\n")).collect();
+    let new: String = (1..=20)
+        .map(|i| match i {
+            2 => "two\n".to_owned(),
+            19 => "nineteen\n".to_owned(),
+            _ => format!("{i}\n"),
+        })
+        .collect();
+    let diff = unified_diff("f", Some(&old), &new);
+    let headers: Vec<&str> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+    assert_eq!(headers, vec!["@@ -1,5 +1,5 @@", "@@ -16,5 +16,5 @@"]);
+}
+
+#[test]
+fn diff_handles_insertions_and_deletions() {
+    let diff = unified_diff("f", Some("a\nb\nc\n"), "a\nc\nd\n");
+    assert_eq!(diff, "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n-b\n c\n+d\n");
+}
+
+#[test]
+fn diff_of_new_file() {
+    assert_eq!(
+        unified_diff("new.txt", None, "hello\nworld\n"),
+        "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+hello\n+world\n"
+    );
+}
+
+#[test]
+fn diff_of_large_rewrite_replaces_everything() {
+    let old: String = (0..100_000).map(|i| format!("old {i}\n")).collect();
+    let new: String = (0..100_000).map(|i| format!("new {i}\n")).collect();
+    let diff = unified_diff("f", Some(&old), &new);
+    assert!(diff.starts_with("--- a/f\n+++ b/f\n@@ -1,100000 +1,100000 @@\n-old 0\n"));
+    // Both counts include the file header.
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with('-')).count(),
+        100_001
+    );
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with('+')).count(),
+        100_001
+    );
+}
+
+#[test]
+fn diff_of_large_file_with_scattered_changes_is_minimal() {
+    let old: Vec<String> = (0..20_000).map(|i| format!("line {i}")).collect();
+    let mut new = old.clone();
+    for i in (0..20_000).step_by(200) {
+        new[i] = format!("changed {i}");
+    }
+    let diff = unified_diff("f", Some(&old.join("\n")), &new.join("\n"));
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with("@@")).count(),
+        100
+    );
+    assert_eq!(
+        diff.lines()
+            .filter(|line| line.starts_with("-line"))
+            .count(),
+        100
+    );
+    assert_eq!(
+        diff.lines()
+            .filter(|line| line.starts_with("+changed"))
+            .count(),
+        100
+    );
+}
+
+#[test]
+fn plan_entries_are_rendered_with_titles() {
+    let plan = Plan::new(vec![
+        PlanEntry::new(
+            "Write tests",
+            PlanEntryPriority::High,
+            PlanEntryStatus::Completed,
+        ),
+        PlanEntry::new(
+            "Fix bug",
+            PlanEntryPriority::High,
+            PlanEntryStatus::InProgress,
+        ),
+        PlanEntry::new(
+            "Update docs",
+            PlanEntryPriority::Low,
+            PlanEntryStatus::Pending,
+        ),
+    ]);
+    assert_eq!(
+        render_plan(&plan),
+        "\n[plan]\n  [x] Write tests\n  [>] Fix bug\n  [ ] Update docs\n"
+    );
+}
@@ -0,0 +1,129 @@
+//! Confines the agent's file system access to its workspace.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use agent_client_protocol::Error;
+
+mod tests;
+
+/// How many symlinks may be followed while resolving a path, as on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The paths the agent may access through the ACP file system methods.
+///
+/// The agent may read and write inside the workspace root and may additionally read
+/// from an allowlist of paths. Paths are resolved including symlinks before checking them.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+    readable: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(root: &Path) -> io::Result<Self> {
+        Ok(Self {
+            root: root.canonicalize()?,
+            readable: Vec::new(),
+        })
+    }
+
+    /// Additionally allow reading the given files or directories.
+    /// Paths that do not exist are ignored.
+    pub fn readable_paths(mut self, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        for path in paths {
+            match path.canonicalize() {
+                Ok(path) => self.readable.push(path),
+                Err(err) => eprintln!("[sandbox] ignoring {}: {err}", path.display()),
+            }
+        }
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a path the agent wants to read from.
+    pub fn resolve_read(&self, path: &Path) -> Result<PathBuf, Error> {
+        let resolved = self.resolve(path)?;
+        if resolved.starts_with(&self.root)
+            || self
+                .readable
+                .iter()
+                .any(|allowed| resolved.starts_with(allowed))
+        {
+            Ok(resolved)
+        } else {
+            Err(outside_error(path))
+        }
+    }
+
+    /// Resolve a path the agent wants to write to.
+    pub fn resolve_write(&self, path: &Path) -> Result<PathBuf, Error> {
+        let resolved = self.resolve(path)?;
+        if resolved.starts_with(&self.root) {
+            Ok(resolved)
+        } else {
+            Err(outside_error(path))
+        }
+    }
+
+    /// Make a path absolute and resolve all symlinks and `..` components.
+    ///
+    /// Components are resolved one at a time, like the kernel does. Symlinks are followed even
+    /// if their target does not exist (yet), since writing through a dangling symlink creates
+    /// the target. Components that do not exist are taken as they are.
+    fn resolve(&self, path: &Path) -> Result<PathBuf, Error> {
+        let mut pending: Vec<OsString> = components(&self.root.join(path));
+        let mut resolved = PathBuf::new();
+        let mut links = 0;
+        while let Some(component) = pending.pop() {
+            match Path::new(&component).components().next() {
+                Some(Component::CurDir) | None => {}
+                Some(Component::ParentDir) => {
+                    resolved.pop();
+                }
+                Some(Component::Normal(name)) => {
+                    let candidate = resolved.join(name);
+                    match candidate.symlink_metadata() {
+                        Ok(metadata) if metadata.is_symlink() => {
+                            links += 1;
+                            if links > MAX_SYMLINKS {
+                                return Err(Error::invalid_params().data(format!(
+                                    "too many levels of symbolic links in {}",
+                                    path.display()
+                                )));
+                            }
+                            let target =
+                                candidate.read_link().map_err(Error::into_internal_error)?;
+                            pending.extend(components(&target));
+                        }
+                        Ok(_) => resolved = candidate,
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => resolved = candidate,
+                        Err(err) => return Err(Error::into_internal_error(err)),
+                    }
+                }
+                // The root directory (or a prefix) replaces everything resolved so far.
+                Some(_) => resolved.push(&component),
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// The components of a path in reverse order, to be popped off one by one.
+fn components(path: &Path) -> Vec<OsString> {
+    path.components()
+        .rev()
+        .map(|component| component.as_os_str().to_owned())
+        .collect()
+}
+
+fn outside_error(path: &Path) -> Error {
+    Error::invalid_params().data(format!(
+        "access denied: {} is outside of the workspace",
+        path.display()
+    ))
+}
@@ -0,0 +1,165 @@
+//! Tests for the path checks of the `Sandbox`.
+#![cfg(test)]
+
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+use super::Sandbox;
+
+/// Create a directory containing a workspace with a file and an outside file next to it.
+fn fixture() -> (TempDir, Sandbox) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("workspace")).unwrap();
+    fs::write(dir.path().join("workspace/file.txt"), "inside").unwrap();
+    fs::write(dir.path().join("secret.txt"), "outside").unwrap();
+    let sandbox = Sandbox::new(&dir.path().join("workspace")).unwrap();
+    (dir, sandbox)
+}
+
+#[test]
+fn relative_path_inside() {
+    let (dir, sandbox) = fixture();
+    let resolved = sandbox.resolve_read(Path::new("file.txt")).unwrap();
+    assert_eq!(
+        resolved,
+        dir.path()
+            .join("workspace/file.txt")
+            .canonicalize()
+            .unwrap()
+    );
+}
+
+#[test]
+fn absolute_path_inside() {
+    let (dir, sandbox) = fixture();
+    let path = dir.path().join("workspace/file.txt");
+    assert!(sandbox.resolve_read(&path).is_ok());
+    assert!(sandbox.resolve_write(&path).is_ok());
+}
+
+#[test]
+fn new_file_inside() {
+    let (dir, sandbox) = fixture();
+    let resolved = sandbox
+        .resolve_write(Path::new("new/dir/file.txt"))
+        .unwrap();
+    assert_eq!(
+        resolved,
+        dir.path()
+            .canonicalize()
+            .unwrap()
+            .join("workspace/new/dir/file.txt")
+    );
+}
+
+#[test]
+fn parent_dir_traversal() {
+    let (_dir, sandbox) = fixture();
+    assert!(sandbox.resolve_read(Path::new("../secret.txt")).is_err());
+    assert!(sandbox.resolve_write(Path::new("../secret.txt")).is_err());
+    assert!(
+        sandbox
+            .resolve_write(Path::new("new/../../secret.txt"))
+            .is_err()
+    );
+}
+
+#[test]
+fn parent_dir_staying_inside() {
+    let (_dir, sandbox) = fixture();
+    assert!(sandbox.resolve_read(Path::new("new/../file.txt")).is_ok());
+}
+
+#[test]
+fn absolute_path_outside() {
+    let (_dir, sandbox) = fixture();
+    assert!(sandbox.resolve_read(Path::new("/etc/passwd")).is_err());
+    assert!(sandbox.resolve_write(Path::new("/tmp/file.txt")).is_err());
+}
+
+#[test]
+fn sibling_with_common_prefix() {
+    let (dir, sandbox) = fixture();
+    fs::create_dir(dir.path().join("workspace-other")).unwrap();
+    let path = dir.path().join("workspace-other/file.txt");
+    assert!(sandbox.resolve_write(&path).is_err());
+}
+
+#[test]
+fn symlink_escape() {
+    let (dir, sandbox) = fixture();
+    symlink(
+        dir.path().join("secret.txt"),
+        dir.path().join("workspace/link.txt"),
+    )
+    .unwrap();
+    symlink(dir.path(), dir.path().join("workspace/link-dir")).unwrap();
+    assert!(sandbox.resolve_read(Path::new("link.txt")).is_err());
+    assert!(sandbox.resolve_write(Path::new("link.txt")).is_err());
+    assert!(
+        sandbox
+            .resolve_read(Path::new("link-dir/secret.txt"))
+            .is_err()
+    );
+    assert!(
+        sandbox
+            .resolve_write(Path::new("link-dir/new.txt"))
+            .is_err()
+    );
+}
+
+#[test]
+fn symlink_inside() {
+    let (dir, sandbox) = fixture();
+    symlink(
+        dir.path().join("workspace/file.txt"),
+        dir.path().join("workspace/link.txt"),
+    )
+    .unwrap();
+    assert!(sandbox.resolve_write(Path::new("link.txt")).is_ok());
+}
+
+#[test]
+fn readable_allowlist() {
+    let (dir, sandbox) = fixture();
+    let sandbox = sandbox.readable_paths([dir.path().join("secret.txt")]);
+    assert!(sandbox.resolve_read(Path::new("../secret.txt")).is_ok());
+    assert!(sandbox.resolve_write(Path::new("../secret.txt")).is_err());
+}
+
+#[test]
+fn dangling_symlink_escape() {
+    let (dir, sandbox) = fixture();
+    symlink(
+        dir.path().join("cron.d/job"),
+        dir.path().join("workspace/evil"),
+    )
+    .unwrap();
+    symlink("../missing", dir.path().join("workspace/relative-evil")).unwrap();
+    assert!(sandbox.resolve_write(Path::new("evil")).is_err());
+    assert!(sandbox.resolve_write(Path::new("relative-evil")).is_err());
+}
+
+#[test]
+fn dangling_symlink_inside() {
+    let (dir, sandbox) = fixture();
+    symlink("new/file.txt", dir.path().join("workspace/link.txt")).unwrap();
+    assert_eq!(
+        sandbox.resolve_write(Path::new("link.txt")).unwrap(),
+        dir.path()
+            .canonicalize()
+            .unwrap()
+            .join("workspace/new/file.txt")
+    );
+}
+
+#[test]
+fn symlink_loop() {
+    let (dir, sandbox) = fixture();
+    symlink("b", dir.path().join("workspace/a")).unwrap();
+    symlink("a", dir.path().join("workspace/b")).unwrap();
+    assert!(sandbox.resolve_write(Path::new("a")).is_err());
+}
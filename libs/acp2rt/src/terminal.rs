@@ -0,0 +1,219 @@
+//! Commands that the agent runs through the ACP `terminal/*` methods.
+
+use std::collections::HashMap;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use agent_client_protocol::{CreateTerminalRequest, Error, TerminalExitStatus, TerminalId};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command as TokioCommand;
+use tokio::sync::{oneshot, watch};
+
+use crate::AcpResult;
+
+mod tests;
+
+/// Upper limit for the retained output of a single terminal, regardless of what the agent asks for.
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+/// How long to wait for remaining output after a command exited. Background processes may keep
+/// the output pipes open for longer.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The terminals of a session, keyed by their ID.
+#[derive(Debug, Default)]
+pub(crate) struct Terminals {
+    next_id: AtomicU64,
+    terminals: Mutex<HashMap<TerminalId, Terminal>>,
+}
+
+#[derive(Debug)]
+struct Terminal {
+    output: Arc<Mutex<Output>>,
+    exit_status: watch::Receiver<Option<TerminalExitStatus>>,
+    /// Kills the command when sent to or dropped.
+    kill: Option<oneshot::Sender<()>>,
+}
+
+/// Output of a command, truncated from the beginning to stay within a byte limit.
+#[derive(Debug)]
+struct Output {
+    bytes: Vec<u8>,
+    limit: usize,
+    truncated: bool,
+}
+
+impl Terminals {
+    /// Spawn a command in `cwd`. Must be called from within a `LocalSet`.
+    pub(crate) fn create(&self, args: &CreateTerminalRequest, cwd: &Path) -> AcpResult<TerminalId> {
+        let mut child = TokioCommand::new(&args.command)
+            .args(&args.args)
+            .envs(args.env.iter().map(|var| (&var.name, &var.value)))
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| {
+                Error::invalid_params().data(format!("failed to run {}: {err}", args.command))
+            })?;
+
+        let limit = args
+            .output_byte_limit
+            .map_or(MAX_OUTPUT_BYTES, |limit| {
+                usize::try_from(limit).unwrap_or(usize::MAX)
+            })
+            .min(MAX_OUTPUT_BYTES);
+        let output = Arc::new(Mutex::new(Output {
+            bytes: Vec::new(),
+            limit,
+            truncated: false,
+        }));
+        let stdout = child.stdout.take().map(|out| capture(out, output.clone()));
+        let stderr = child.stderr.take().map(|err| capture(err, output.clone()));
+
+        let (exit_tx, exit_rx) = watch::channel(None);
+        let (kill_tx, mut kill_rx) = oneshot::channel();
+        tokio::task::spawn_local(async move {
+            let status = tokio::select! {
+                status = child.wait() => Some(status),
+                _ = &mut kill_rx => None,
+            };
+            let status = match status {
+                Some(status) => status,
+                None => {
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            };
+            // Make sure all output is captured before reporting the exit.
+            for reader in [stdout, stderr].into_iter().flatten() {
+                let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, reader).await;
+            }
+            let _ = exit_tx.send(Some(match status {
+                Ok(status) => exit_status(status),
+                Err(_) => TerminalExitStatus::new(),
+            }));
+        });
+
+        let id = TerminalId::new(format!(
+            "terminal-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        ));
+        self.terminals.lock().unwrap().insert(
+            id.clone(),
+            Terminal {
+                output,
+                exit_status: exit_rx,
+                kill: Some(kill_tx),
+            },
+        );
+        Ok(id)
+    }
+
+    /// The output so far, whether it was truncated, and the exit status if the command exited.
+    pub(crate) fn output(
+        &self,
+        id: &TerminalId,
+    ) -> AcpResult<(String, bool, Option<TerminalExitStatus>)> {
+        let terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get(id).ok_or_else(|| unknown_terminal(id))?;
+        let output = terminal.output.lock().unwrap();
+        Ok((
+            String::from_utf8_lossy(&output.bytes).into_owned(),
+            output.truncated,
+            terminal.exit_status.borrow().clone(),
+        ))
+    }
+
+    pub(crate) async fn wait_for_exit(&self, id: &TerminalId) -> AcpResult<TerminalExitStatus> {
+        let mut exit_status = {
+            let terminals = self.terminals.lock().unwrap();
+            let terminal = terminals.get(id).ok_or_else(|| unknown_terminal(id))?;
+            terminal.exit_status.clone()
+        };
+        let status = exit_status
+            .wait_for(Option::is_some)
+            .await
+            .map_err(Error::into_internal_error)?;
+        Ok(status.clone().unwrap_or_default())
+    }
+
+    /// Kill the command but keep the terminal, so its output can still be retrieved.
+    pub(crate) fn kill(&self, id: &TerminalId) -> AcpResult<()> {
+        let mut terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get_mut(id).ok_or_else(|| unknown_terminal(id))?;
+        if let Some(kill) = terminal.kill.take() {
+            let _ = kill.send(());
+        }
+        Ok(())
+    }
+
+    /// Kill the command if it is still running and forget the terminal.
+    pub(crate) fn release(&self, id: &TerminalId) -> AcpResult<()> {
+        self.terminals
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map(drop)
+            .ok_or_else(|| unknown_terminal(id))
+    }
+}
+
+impl Output {
+    fn push(&mut self, chunk: &[u8]) {
+        self.bytes.extend_from_slice(chunk);
+        if self.bytes.len() > self.limit {
+            let mut excess = self.bytes.len() - self.limit;
+            // Don't start the retained output in the middle of a UTF-8 character.
+            while excess < self.bytes.len() && is_utf8_continuation(self.bytes[excess]) {
+                excess += 1;
+            }
+            self.bytes.drain(..excess);
+            self.truncated = true;
+        }
+    }
+}
+
+fn is_utf8_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+fn capture(
+    mut reader: impl AsyncRead + Unpin + 'static,
+    output: Arc<Mutex<Output>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::task::spawn_local(async move {
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = reader.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+            output.lock().unwrap().push(&buf[..n]);
+        }
+    })
+}
+
+fn exit_status(status: ExitStatus) -> TerminalExitStatus {
+    TerminalExitStatus::new()
+        .exit_code(status.code().map(|code| code as u32))
+        .signal(status.signal().map(signal_name))
+}
+
+fn signal_name(signal: i32) -> String {
+    match signal {
+        1 => "SIGHUP".to_owned(),
+        2 => "SIGINT".to_owned(),
+        9 => "SIGKILL".to_owned(),
+        15 => "SIGTERM".to_owned(),
+        other => format!("signal {other}"),
+    }
+}
+
+fn unknown_terminal(id: &TerminalId) -> Error {
+    Error::invalid_params().data(format!("unknown terminal: {id}"))
+}
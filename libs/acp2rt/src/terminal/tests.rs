@@ -0,0 +1,145 @@
+//! Tests for running commands in terminals, using `sh` as the command.
+#![cfg(test)]
+
+use std::time::Duration;
+
+use agent_client_protocol::{CreateTerminalRequest, TerminalId};
+use tempfile::TempDir;
+use tokio::task::LocalSet;
+
+use super::{Output, Terminals};
+
+fn sh(script: &str) -> CreateTerminalRequest {
+    CreateTerminalRequest::new("session", "sh").args(vec!["-c".to_owned(), script.to_owned()])
+}
+
+fn output(terminals: &Terminals, id: &TerminalId) -> String {
+    terminals.output(id).unwrap().0
+}
+
+#[test]
+fn truncation_keeps_the_end_of_the_output() {
+    let mut output = Output {
+        bytes: Vec::new(),
+        limit: 4,
+        truncated: false,
+    };
+    output.push(b"ab");
+    assert!(!output.truncated);
+    output.push("c€".as_bytes());
+    assert_eq!(output.bytes, "c€".as_bytes());
+    assert!(output.truncated);
+}
+
+#[test]
+fn truncation_does_not_split_characters() {
+    let mut output = Output {
+        bytes: Vec::new(),
+        limit: 3,
+        truncated: false,
+    };
+    // Keeping the last three bytes would start in the middle of the euro sign.
+    output.push("a€b".as_bytes());
+    assert_eq!(output.bytes, b"b");
+    assert!(output.truncated);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn output_byte_limit_is_applied() {
+    let dir = TempDir::new().unwrap();
+    let terminals = Terminals::default();
+    LocalSet::new()
+        .run_until(async {
+            let id = terminals
+                .create(
+                    &sh("printf 'hello '; printf 'world' >&2").output_byte_limit(5),
+                    dir.path(),
+                )
+                .unwrap();
+            terminals.wait_for_exit(&id).await.unwrap();
+            let (output, truncated, _) = terminals.output(&id).unwrap();
+            assert_eq!(output, "world");
+            assert!(truncated);
+        })
+        .await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn wait_for_exit_reports_the_exit_code() {
+    let dir = TempDir::new().unwrap();
+    let terminals = Terminals::default();
+    LocalSet::new()
+        .run_until(async {
+            let id = terminals.create(&sh("pwd; exit 3"), dir.path()).unwrap();
+            let status = terminals.wait_for_exit(&id).await.unwrap();
+            assert_eq!(status.exit_code, Some(3));
+            assert_eq!(status.signal, None);
+
+            let (output, truncated, exit_status) = terminals.output(&id).unwrap();
+            assert_eq!(
+                output.trim_end(),
+                dir.path().canonicalize().unwrap().to_str().unwrap()
+            );
+            assert!(!truncated);
+            assert_eq!(exit_status, Some(status));
+        })
+        .await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn killed_terminals_keep_their_output() {
+    let dir = TempDir::new().unwrap();
+    let terminals = Terminals::default();
+    LocalSet::new()
+        .run_until(async {
+            let id = terminals
+                .create(&sh("echo started; exec sleep 30"), dir.path())
+                .unwrap();
+            while output(&terminals, &id).is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            terminals.kill(&id).unwrap();
+
+            let status = terminals.wait_for_exit(&id).await.unwrap();
+            assert_eq!(status.signal.as_deref(), Some("SIGKILL"));
+            assert_eq!(output(&terminals, &id), "started\n");
+            // Killing again is harmless.
+            terminals.kill(&id).unwrap();
+        })
+        .await;
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn released_terminals_are_killed_and_forgotten() {
+    let dir = TempDir::new().unwrap();
+    let terminals = Terminals::default();
+    LocalSet::new()
+        .run_until(async {
+            let id = terminals
+                .create(&sh("echo $$; exec sleep 30"), dir.path())
+                .unwrap();
+            let pid = loop {
+                let output = output(&terminals, &id);
+                if !output.is_empty() {
+                    break output.trim().to_owned();
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            terminals.release(&id).unwrap();
+
+            assert!(terminals.output(&id).is_err());
+            assert!(terminals.wait_for_exit(&id).await.is_err());
+            assert!(terminals.kill(&id).is_err());
+            assert!(terminals.release(&id).is_err());
+
+            let proc = std::path::PathBuf::from(format!("/proc/{pid}"));
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while proc.exists() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("the command is still running");
+        })
+        .await;
+}
@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use reqwest::{Body, RequestBuilder, Response, StatusCode, Url};
+use uuid::Uuid;
+
+use crate::result::{ClientError, Result};
+use crate::types::artifact::*;
+use crate::types::info::*;
+use crate::types::inquiry::*;
+use crate::types::session::*;
+use crate::types::task::*;
+
+mod tests;
+
+/// How often and how patiently requests are retried after transient failures, i.e.
+/// connection problems, timeouts and `429`/`5xx` responses.
+///
+/// Only requests that are safe to repeat are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt. Zero disables retries.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: Url,
+    token: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    retry_policy: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// Timeout for each request. Inquiries and artifact uploads are exempt, since they wait for
+    /// the user or may take long for large files.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut client = reqwest::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        Ok(Client {
+            base_url: self.base_url,
+            token: self.token,
+            client: client.build()?,
+            timeout: self.timeout,
+            retry_policy: self.retry_policy,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: Url,
+    token: String,
+    client: reqwest::Client,
+    timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+}
+
+/// How a request is treated on failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    /// Safe to repeat, so it is retried after transient failures.
+    Idempotent,
+    /// Reports something the runtime acts on, e.g. by shutting down once the task is completed.
+    /// The runtime may have received it even if the response was lost, so it is not repeated.
+    Report,
+    /// Waits for the user, so it is neither repeated nor subject to the timeout.
+    Interactive,
+    /// Streams a body that cannot be replayed, so it is neither repeated nor subject to the
+    /// timeout.
+    Once,
+}
+
+impl Client {
+    pub fn new(base_url: Url, token: String) -> Self {
+        Self::builder(base_url, token)
+            .build()
+            .expect("Failed to build HTTP client")
+    }
+
+    /// Configure a client. By default, requests time out after 30 seconds and idempotent
+    /// requests are retried according to [`RetryPolicy::default`].
+    pub fn builder(base_url: Url, token: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url,
+            token: token.into(),
+            timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(10)),
+            user_agent: concat!("agent-api/", env!("CARGO_PKG_VERSION")).to_owned(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// The API version and capabilities of the runtime.
+    pub async fn info(&self) -> Result<RuntimeInfo> {
+        let url = self.base_url.join("agent/info")?;
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<RuntimeInfo>().await?)
+    }
+
+    /// The optional endpoints the runtime supports. Runtimes that predate `GET /agent/info`
+    /// are assumed to support none of them.
+    pub async fn capabilities(&self) -> Result<Capabilities> {
+        match self.info().await {
+            Ok(info) => Ok(info.capabilities),
+            Err(ClientError::NotFound(_)) => Ok(Capabilities::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn get_task(&self) -> Result<Task> {
+        let url = self.base_url.join("agent/task")?;
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<Task>().await?)
+    }
+
+    /// Fetch the task and claim it if it is queued. The task is `Queued` only for the agent
+    /// that claimed it, even if the request had to be retried.
+    pub async fn claim_task(&self) -> Result<Task> {
+        let mut url = self.base_url.join("agent/task")?;
+        url.query_pairs_mut()
+            .append_pair("claim", &Uuid::new_v4().to_string());
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<Task>().await?)
+    }
+
+    /// The status of the task. Cheaper than [`Client::get_task`], e.g. to check for a
+    /// cancellation while working on the task.
+    pub async fn task_status(&self) -> Result<TaskStatus> {
+        let url = self.base_url.join("agent/task/status")?;
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<TaskState>().await?.status)
+    }
+
+    /// Report that the task is completed. Not retried, since the runtime shuts down once it
+    /// received the outcome.
+    pub async fn complete_task(&self, task_complete: TaskComplete) -> Result<()> {
+        let url = self.base_url.join("agent/task/complete")?;
+        self.execute(RequestKind::Report, |client| {
+            client.post(url.clone()).json(&task_complete)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Get the user's messages for the task, optionally only those after a given message ID.
+    pub async fn task_messages(&self, after: Option<u64>) -> Result<Vec<TaskMessage>> {
+        let mut url = self.base_url.join("agent/task/messages")?;
+        if let Some(after) = after {
+            url.query_pairs_mut()
+                .append_pair("after", &after.to_string());
+        }
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<Vec<TaskMessage>>().await?)
+    }
+
+    /// Report progress on the task. Not retried, since the user would see it twice.
+    pub async fn report_progress(&self, task_progress: TaskProgress) -> Result<()> {
+        let url = self.base_url.join("agent/task/progress")?;
+        self.execute(RequestKind::Report, |client| {
+            client.post(url.clone()).json(&task_progress)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Report that the task failed. Not retried, like [`Client::complete_task`].
+    pub async fn fail_task(&self, task_fail: TaskFailure) -> Result<()> {
+        let url = self.base_url.join("agent/task/fail")?;
+        self.execute(RequestKind::Report, |client| {
+            client.post(url.clone()).json(&task_fail)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Ask the user a question and wait for the answer.
+    ///
+    /// Not retried, since a repeated request would ask the question again.
+    pub async fn inquire(&self, question: impl Into<String>) -> Result<String> {
+        let url = self.base_url.join("agent/inquiry")?;
+        let inquiry = Inquiry {
+            inquiry: question.into(),
+        };
+        let response = self
+            .execute(RequestKind::Interactive, |client| {
+                client.post(url.clone()).json(&inquiry)
+            })
+            .await?;
+        Ok(response.json::<InquiryAnswer>().await?.answer)
+    }
+
+    /// The session saved with [`Client::save_session`], if any.
+    pub async fn session(&self) -> Result<Option<SessionState>> {
+        let url = self.base_url.join("agent/session")?;
+        let response = self
+            .execute(RequestKind::Idempotent, |client| client.get(url.clone()))
+            .await?;
+        Ok(response.json::<Option<SessionState>>().await?)
+    }
+
+    /// Save the agent's session, so it can be resumed if the agent or its container restarts.
+    pub async fn save_session(&self, session: SessionState) -> Result<()> {
+        let url = self.base_url.join("agent/session")?;
+        self.execute(RequestKind::Idempotent, |client| {
+            client.put(url.clone()).json(&session)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Upload a file for the user that does not belong in git, e.g. a test report.
+    ///
+    /// The body may be streamed, e.g. from a [`tokio::fs::File`]. Not retried, since a
+    /// streamed body cannot be sent again. Fails with [`ClientError::Server`] and status `413`
+    /// if the artifact exceeds the runtime's size limits.
+    pub async fn upload_artifact(&self, name: &str, body: impl Into<Body>) -> Result<Artifact> {
+        let mut url = self.base_url.join("agent/artifacts")?;
+        url.query_pairs_mut().append_pair("name", name);
+        let mut body = Some(body.into());
+        let response = self
+            .execute(RequestKind::Once, |client| {
+                let body = body.take().expect("Artifact uploads are sent only once");
+                client.post(url.clone()).body(body)
+            })
+            .await?;
+        Ok(response.json::<Artifact>().await?)
+    }
+
+    /// Send an authenticated request and map error responses onto [`ClientError`] variants.
+    async fn execute(
+        &self,
+        kind: RequestKind,
+        mut request: impl FnMut(&reqwest::Client) -> RequestBuilder,
+    ) -> Result<Response> {
+        let mut retry = 0;
+        loop {
+            let mut builder = request(&self.client).bearer_auth(&self.token);
+            let timed = matches!(kind, RequestKind::Idempotent | RequestKind::Report);
+            if let Some(timeout) = self.timeout.filter(|_| timed) {
+                builder = builder.timeout(timeout);
+            }
+            let result = builder.send().await;
+
+            let transient = match &result {
+                Ok(response) => {
+                    let status = response.status();
+                    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                }
+                Err(err) => err.is_connect() || err.is_timeout(),
+            };
+            if transient && kind == RequestKind::Idempotent && retry < self.retry_policy.max_retries
+            {
+                tokio::time::sleep(self.retry_policy.backoff(retry)).await;
+                retry += 1;
+                continue;
+            }
+
+            return error_for_status(result?).await;
+        }
+    }
+}
+
+async fn error_for_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ClientError::Unauthorized),
+        StatusCode::NOT_FOUND => Err(ClientError::NotFound(response.url().path().to_owned())),
+        _ => {
+            let message = response.text().await.unwrap_or_default();
+            Err(ClientError::Server {
+                status: status.as_u16(),
+                message,
+            })
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Tests for the client against a local stand-in for the runtime.
+#![cfg(test)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::Query;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use super::{Client, RetryPolicy};
+use crate::result::ClientError;
+use crate::types::artifact::{Artifact, ArtifactQuery};
+use crate::types::info::Capabilities;
+use crate::types::task::{Task, TaskComplete, TaskQuery, TaskStatus};
+
+/// Serve `router` on a free local port and return the base URL of its API.
+async fn serve(router: Router) -> reqwest::Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}/api/").parse().unwrap()
+}
+
+fn client(base_url: reqwest::Url) -> Client {
+    Client::builder(base_url, "token")
+        .retry_policy(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        })
+        .build()
+        .unwrap()
+}
+
+fn task() -> Task {
+    Task {
+        status: TaskStatus::Running,
+        description: "Do the thing".to_owned(),
+        git_user_name: "minion".to_owned(),
+        git_user_email: "minion@localhost".to_owned(),
+        git_repo_url: "http://localhost/repo.git".parse().unwrap(),
+        git_branch: "main".to_owned(),
+        permission_policy: None,
+        mcp_servers: Vec::new(),
+        mode: None,
+        model: None,
+        attachments: Vec::new(),
+        references: Vec::new(),
+        acceptance_criteria: Vec::new(),
+    }
+}
+
+/// A route that fails with `status` for the first `failures` requests, then returns a task.
+fn flaky_task_route(failures: u32, status: StatusCode, calls: Arc<AtomicU32>) -> Router {
+    Router::new().route(
+        "/api/agent/task",
+        get(move || {
+            let calls = calls.clone();
+            async move {
+                if calls.fetch_add(1, Ordering::SeqCst) < failures {
+                    Err((status, "try again later"))
+                } else {
+                    Ok(Json(task()))
+                }
+            }
+        }),
+    )
+}
+
+#[tokio::test]
+async fn retries_transient_failures() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let url = serve(flaky_task_route(
+        2,
+        StatusCode::SERVICE_UNAVAILABLE,
+        calls.clone(),
+    ))
+    .await;
+
+    let task = client(url).get_task().await.unwrap();
+    assert_eq!(task.description, "Do the thing");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let url = serve(flaky_task_route(
+        10,
+        StatusCode::SERVICE_UNAVAILABLE,
+        calls.clone(),
+    ))
+    .await;
+
+    let err = client(url).get_task().await.err().unwrap();
+    assert!(matches!(
+        err,
+        ClientError::Server { status: 503, ref message } if message == "try again later"
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let url = serve(flaky_task_route(10, StatusCode::BAD_REQUEST, calls.clone())).await;
+
+    let err = client(url).get_task().await.err().unwrap();
+    assert!(matches!(err, ClientError::Server { status: 400, .. }));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn inquiries_are_not_retried() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let router = Router::new().route(
+        "/api/agent/inquiry",
+        post({
+            let calls = calls.clone();
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }),
+    );
+    let url = serve(router).await;
+
+    let err = client(url).inquire("Why?").await.err().unwrap();
+    assert!(matches!(err, ClientError::Server { status: 503, .. }));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn unauthorized() {
+    let router = Router::new().route(
+        "/api/agent/task",
+        get(|| async { StatusCode::UNAUTHORIZED }),
+    );
+    let url = serve(router).await;
+
+    let err = client(url).get_task().await.err().unwrap();
+    assert!(matches!(err, ClientError::Unauthorized));
+}
+
+#[tokio::test]
+async fn not_found() {
+    let url = serve(Router::new()).await;
+
+    let err = client(url).session().await.err().unwrap();
+    assert!(matches!(err, ClientError::NotFound(ref path) if path == "/api/agent/session"));
+}
+
+#[tokio::test]
+async fn sends_token_and_user_agent() {
+    let router = Router::new().route(
+        "/api/agent/task",
+        get(|headers: HeaderMap| async move {
+            let authorized = headers["authorization"] == "Bearer token";
+            let user_agent = headers["user-agent"] == "test-agent/1.0";
+            if authorized && user_agent {
+                Ok(Json(task()))
+            } else {
+                Err(StatusCode::BAD_REQUEST)
+            }
+        }),
+    );
+    let url = serve(router).await;
+
+    let client = Client::builder(url, "token")
+        .user_agent("test-agent/1.0")
+        .build()
+        .unwrap();
+    client.get_task().await.unwrap();
+}
+
+#[tokio::test]
+async fn requests_time_out() {
+    let router = Router::new().route(
+        "/api/agent/task",
+        get(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Json(task())
+        }),
+    );
+    let url = serve(router).await;
+
+    let client = Client::builder(url, "token")
+        .timeout(Duration::from_millis(50))
+        .retry_policy(RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    let err = client.get_task().await.err().unwrap();
+    assert!(matches!(err, ClientError::Reqwest(ref err) if err.is_timeout()));
+}
+
+#[tokio::test]
+async fn capabilities_of_runtime_without_info_endpoint() {
+    let url = serve(Router::new()).await;
+
+    let capabilities = client(url).capabilities().await.unwrap();
+    assert_eq!(capabilities, Capabilities::default());
+}
+
+#[tokio::test]
+async fn task_from_newer_runtime() {
+    let router = Router::new().route(
+        "/api/agent/task",
+        get(|| async {
+            let mut task = serde_json::to_value(task()).unwrap();
+            task["status"] = "Paused".into();
+            task["priority"] = "high".into();
+            Json(task)
+        }),
+    );
+    let url = serve(router).await;
+
+    let task = client(url).get_task().await.unwrap();
+    assert_eq!(task.status, TaskStatus::Unknown);
+    assert_eq!(task.description, "Do the thing");
+}
+
+#[tokio::test]
+async fn uploads_artifacts_once() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let router = Router::new().route(
+        "/api/agent/artifacts",
+        post({
+            let calls = calls.clone();
+            move |Query(query): Query<ArtifactQuery>, body: Bytes| async move {
+                if calls.fetch_add(1, Ordering::SeqCst) > 0 {
+                    return Err(StatusCode::SERVICE_UNAVAILABLE);
+                }
+                assert_eq!(&body[..], b"all tests passed");
+                Ok(Json(Artifact {
+                    name: query.name,
+                    size: body.len() as u64,
+                }))
+            }
+        }),
+    );
+    let url = serve(router).await;
+    let client = client(url);
+
+    let artifact = client
+        .upload_artifact("test report.txt", "all tests passed")
+        .await
+        .unwrap();
+    assert_eq!(artifact.name, "test report.txt");
+    assert_eq!(artifact.size, 16);
+
+    let err = client.upload_artifact("again.txt", "").await.err().unwrap();
+    assert!(matches!(err, ClientError::Server { status: 503, .. }));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn task_status_without_the_task() {
+    let router = Router::new().route(
+        "/api/agent/task/status",
+        get(|| async { Json(serde_json::json!({ "status": "Cancelled" })) }),
+    );
+    let url = serve(router).await;
+
+    let status = client(url).task_status().await.unwrap();
+    assert_eq!(status, TaskStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn claims_survive_retries() {
+    let claims = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let router = Router::new().route(
+        "/api/agent/task",
+        get({
+            let claims = claims.clone();
+            move |Query(query): Query<TaskQuery>| async move {
+                let mut claims = claims.lock().unwrap();
+                claims.push(query.claim.clone().unwrap());
+                // The first request claims the task, but its response is lost.
+                if claims.len() == 1 {
+                    return Err(StatusCode::BAD_GATEWAY);
+                }
+                let mut task = task();
+                if query.claim.as_ref() == claims.first() {
+                    task.status = TaskStatus::Queued;
+                }
+                Ok(Json(task))
+            }
+        }),
+    );
+    let url = serve(router).await;
+    let client = client(url);
+
+    let task = client.claim_task().await.unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    let claims = claims.lock().unwrap().clone();
+    assert_eq!(claims.len(), 2);
+    assert_eq!(claims[0], claims[1]);
+
+    let task = client.claim_task().await.unwrap();
+    assert_eq!(task.status, TaskStatus::Running);
+}
+
+#[tokio::test]
+async fn outcomes_are_reported_once() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let router = Router::new().route(
+        "/api/agent/task/complete",
+        post({
+            let calls = calls.clone();
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                StatusCode::BAD_GATEWAY
+            }
+        }),
+    );
+    let url = serve(router).await;
+
+    let err = client(url)
+        .complete_task(TaskComplete {
+            description: "Done".to_owned(),
+        })
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, ClientError::Server { status: 502, .. }));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
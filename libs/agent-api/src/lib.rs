@@ -2,4 +2,4 @@ mod client;
 pub mod result;
 pub mod types;
 
-pub use client::Client;
+pub use client::{Client, ClientBuilder, RetryPolicy};
@@ -11,4 +11,16 @@ pub enum ClientError {
 
     #[error("Request error: {0}")]
     Reqwest(#[from] ReqwestError),
+
+    /// The runtime rejected the API token.
+    #[error("Unauthorized: the runtime rejected the API token")]
+    Unauthorized,
+
+    /// The endpoint does not exist, e.g. because the runtime does not support it.
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Any other error response, with the message of the runtime.
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
 }
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// A file produced by the agent that does not belong in git, e.g. a test report or a
+/// screenshot. The runtime keeps it for the user.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Artifact {
+    pub name: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// Query of an artifact upload. The body of the request is the content of the artifact.
+#[derive(Debug, Deserialize, Serialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArtifactQuery {
+    /// File name of the artifact, without directories. Uploading an artifact with the name of
+    /// an existing one replaces it.
+    pub name: String,
+}
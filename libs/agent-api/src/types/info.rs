@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Version of the agent API described by these types.
+/// Incremented for changes that old agents or runtimes cannot cope with.
+pub const API_VERSION: u32 = 1;
+
+/// What the runtime tells agents about itself.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct RuntimeInfo {
+    pub api_version: u32,
+    /// Name and version of the runtime, e.g. `minion 0.1.0`.
+    pub runtime: String,
+    pub capabilities: Capabilities,
+}
+
+/// Optional endpoints of the agent API that the runtime supports.
+///
+/// Unknown capabilities are ignored and missing ones are unsupported, so agents and runtimes
+/// of different versions understand each other.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(default)]
+pub struct Capabilities {
+    /// `POST /agent/inquiry`
+    pub inquiry: bool,
+    /// `POST /agent/task/progress`
+    pub progress: bool,
+    /// `GET /agent/task/messages`
+    pub messages: bool,
+    /// `GET` and `PUT /agent/session`
+    pub session: bool,
+    /// The task may become `Cancelled` while the agent works on it, as reported by
+    /// `GET /agent/task/status`.
+    pub cancellation: bool,
+    /// `POST /agent/artifacts`
+    pub artifacts: bool,
+}
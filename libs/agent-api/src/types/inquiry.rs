@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+
+/// A question the agent asks the user, e.g. to clarify its task.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct Inquiry {
+    pub inquiry: String,
+}
+
+/// The answer to an [`Inquiry`].
+///
+/// Serialized as a plain JSON string.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(transparent)]
+pub struct InquiryAnswer {
+    pub answer: String,
+}
+
+impl PartialSchema for InquiryAnswer {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("The answer to an inquiry, as a plain JSON string."))
+            .into()
+    }
+}
+
+impl ToSchema for InquiryAnswer {}
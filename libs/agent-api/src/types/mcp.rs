@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+/// An MCP server that provides additional tools to the agent.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpServer {
+    /// A server that the agent starts as a child process
+    Stdio {
+        name: String,
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// A server that the agent connects to via streamable HTTP
+    Http {
+        name: String,
+        url: Url,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl McpServer {
+    pub fn name(&self) -> &str {
+        match self {
+            McpServer::Stdio { name, .. } | McpServer::Http { name, .. } => name,
+        }
+    }
+}
+
+/// A list of MCP servers, as declared in configuration files.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct McpServers {
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+}
@@ -1 +1,7 @@
+pub mod artifact;
+pub mod info;
+pub mod inquiry;
+pub mod mcp;
+pub mod permission;
+pub mod session;
 pub mod task;
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+mod tests;
+
+/// Decides how the permission requests of an agent are answered.
+///
+/// Rules are checked in order and the first matching rule wins.
+/// If no rule matches, the default decision is used.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct PermissionPolicy {
+    #[serde(default)]
+    pub rules: Vec<PermissionRule>,
+    #[serde(default)]
+    pub default: PermissionDecision,
+}
+
+/// Matches permission requests by the kind and title of the tool call.
+/// A rule without any conditions matches every request.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PermissionRule {
+    /// Kind of the tool call as defined by ACP, e.g. `read`, `edit`, `execute` or `fetch`
+    pub kind: Option<String>,
+    /// Case-insensitive substring of the tool call's title
+    pub title_contains: Option<String>,
+    pub decision: PermissionDecision,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    AllowOnce,
+    AllowAlways,
+    Reject,
+    /// Ask the user via the runtime.
+    #[default]
+    Ask,
+}
+
+impl PermissionPolicy {
+    /// Find the decision for a tool call, along with the index of the matching rule.
+    pub fn decide(&self, kind: &str, title: &str) -> (PermissionDecision, Option<usize>) {
+        self.rules
+            .iter()
+            .position(|rule| rule.matches(kind, title))
+            .map(|i| (self.rules[i].decision, Some(i)))
+            .unwrap_or((self.default, None))
+    }
+
+    /// Reject the requests that would be left to the user, for runs in which nobody answers.
+    pub fn without_asking(mut self) -> Self {
+        let reject_asking = |decision: &mut PermissionDecision| {
+            if *decision == PermissionDecision::Ask {
+                *decision = PermissionDecision::Reject;
+            }
+        };
+        self.rules
+            .iter_mut()
+            .for_each(|rule| reject_asking(&mut rule.decision));
+        reject_asking(&mut self.default);
+        self
+    }
+}
+
+impl PermissionRule {
+    fn matches(&self, kind: &str, title: &str) -> bool {
+        let kind_matches = self
+            .kind
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(kind));
+        let title_matches = self.title_contains.as_deref().is_none_or(|needle| {
+            title
+                .to_lowercase()
+                .contains(needle.to_lowercase().as_str())
+        });
+        kind_matches && title_matches
+    }
+}
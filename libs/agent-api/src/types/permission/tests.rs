@@ -0,0 +1,94 @@
+//! Tests for matching tool calls against a `PermissionPolicy`.
+#![cfg(test)]
+
+use super::{PermissionDecision, PermissionPolicy, PermissionRule};
+
+fn rule(
+    kind: Option<&str>,
+    title_contains: Option<&str>,
+    decision: PermissionDecision,
+) -> PermissionRule {
+    PermissionRule {
+        kind: kind.map(str::to_owned),
+        title_contains: title_contains.map(str::to_owned),
+        decision,
+    }
+}
+
+#[test]
+fn rules_match_kind_and_title_case_insensitively() {
+    let both = rule(
+        Some("Execute"),
+        Some("CARGO"),
+        PermissionDecision::AllowOnce,
+    );
+    assert!(both.matches("execute", "Run cargo test"));
+    assert!(!both.matches("edit", "Run cargo test"));
+    assert!(!both.matches("execute", "Run npm test"));
+
+    let any = rule(None, None, PermissionDecision::Reject);
+    assert!(any.matches("fetch", ""));
+}
+
+#[test]
+fn first_matching_rule_wins() {
+    let policy = PermissionPolicy {
+        rules: vec![
+            rule(Some("read"), None, PermissionDecision::AllowAlways),
+            rule(None, Some("rm -rf"), PermissionDecision::Reject),
+            rule(Some("execute"), None, PermissionDecision::AllowOnce),
+        ],
+        default: PermissionDecision::Ask,
+    };
+
+    assert_eq!(
+        policy.decide("read", "Read rm -rf.md"),
+        (PermissionDecision::AllowAlways, Some(0))
+    );
+    assert_eq!(
+        policy.decide("execute", "rm -rf target"),
+        (PermissionDecision::Reject, Some(1))
+    );
+    assert_eq!(
+        policy.decide("execute", "cargo build"),
+        (PermissionDecision::AllowOnce, Some(2))
+    );
+    assert_eq!(
+        policy.decide("edit", "src/main.rs"),
+        (PermissionDecision::Ask, None)
+    );
+}
+
+#[test]
+fn empty_policy_asks() {
+    let policy: PermissionPolicy = serde_json::from_str("{}").unwrap();
+    assert_eq!(
+        policy.decide("execute", "ls"),
+        (PermissionDecision::Ask, None)
+    );
+}
+
+#[test]
+fn without_asking_rejects_what_would_be_asked() {
+    let policy = PermissionPolicy {
+        rules: vec![
+            rule(Some("read"), None, PermissionDecision::AllowAlways),
+            rule(Some("execute"), None, PermissionDecision::Ask),
+        ],
+        default: PermissionDecision::Ask,
+    }
+    .without_asking();
+
+    assert_eq!(
+        policy.decide("read", "README.md"),
+        (PermissionDecision::AllowAlways, Some(0))
+    );
+    assert_eq!(
+        policy.decide("execute", "ls"),
+        (PermissionDecision::Reject, Some(1))
+    );
+    assert_eq!(
+        policy.decide("edit", "src/main.rs"),
+        (PermissionDecision::Reject, None)
+    );
+}
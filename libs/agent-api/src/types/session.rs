@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The agent's ACP session for the task, kept by the runtime so that the agent can resume it
+/// instead of starting over when it or its container restarts.
+///
+/// Only the ID is kept. The agent stores the history of the session itself, e.g. in the
+/// container's file system, so a session cannot be resumed in a replacement container.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct SessionState {
+    /// ID of the ACP session, as assigned by the agent.
+    pub session_id: String,
+}
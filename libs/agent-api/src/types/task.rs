@@ -1,7 +1,16 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use url::Url;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::types::mcp::McpServer;
+use crate::types::permission::PermissionPolicy;
 
-#[derive(Deserialize, Serialize)]
+mod tests;
+
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct Task {
     pub status: TaskStatus,
     pub description: String,
@@ -9,28 +18,144 @@ pub struct Task {
     pub git_user_email: String,
     pub git_repo_url: Url,
     pub git_branch: String,
+    /// How the agent's permission requests are answered.
+    /// If absent, the agent's own configuration applies.
+    #[serde(default)]
+    pub permission_policy: Option<PermissionPolicy>,
+    /// MCP servers to make available to the agent, in addition to its own configuration.
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+    /// Session mode to run the agent in, e.g. `plan` for a run that makes no changes.
+    /// Matched against the IDs and names of the modes the agent offers.
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Model for the agent to use, matched against the IDs and names of the models it offers.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Files the user shared for the task that are not in the repository, e.g. a log or a
+    /// screenshot.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Files or line ranges in the repository that the task is about.
+    #[serde(default)]
+    pub references: Vec<FileReference>,
+    /// Conditions the result must meet for the task to be done.
+    #[serde(default)]
+    pub acceptance_criteria: Vec<String>,
+}
+
+/// A file shared by the user as part of the task.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Attachment {
+    /// File name, without directories.
+    pub name: String,
+    /// MIME type, e.g. `text/plain` or `image/png`.
+    pub mime_type: String,
+    pub content: AttachmentContent,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(tag = "encoding", rename_all = "snake_case")]
+pub enum AttachmentContent {
+    /// Content of a UTF-8 text file.
+    Text { text: String },
+    /// Content of a binary file, base64 encoded.
+    Base64 { data: String },
+}
+
+/// A path in the repository, relative to its root, optionally narrowed to a range of lines.
+///
+/// Written as `path`, `path:line` or `path:start-end` on the command line and in prompts.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct FileReference {
+    pub path: String,
+    #[serde(default)]
+    pub lines: Option<LineRange>,
+}
+
+/// Lines of a file, numbered from 1. Both ends are inclusive.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub struct LineRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl fmt::Display for FileReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lines {
+            Some(LineRange { start, end }) if start == end => write!(f, "{}:{start}", self.path),
+            Some(LineRange { start, end }) => write!(f, "{}:{start}-{end}", self.path),
+            None => f.write_str(&self.path),
+        }
+    }
+}
+
+impl FromStr for FileReference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Colons are allowed in paths, as long as they are not followed by line numbers.
+        let Some((path, lines)) = s.rsplit_once(':').filter(|(_, lines)| {
+            !lines.is_empty() && lines.chars().all(|c| c.is_ascii_digit() || c == '-')
+        }) else {
+            return Ok(Self {
+                path: s.to_owned(),
+                lines: None,
+            });
+        };
+        let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+        let (Ok(start), Ok(end)) = (start.parse::<u32>(), end.parse::<u32>()) else {
+            return Err(format!(
+                "invalid line range `{lines}`, expected `line` or `start-end`"
+            ));
+        };
+        if path.is_empty() {
+            return Err("the path is empty".to_owned());
+        }
+        if start == 0 || start > end {
+            return Err(format!(
+                "invalid line range `{lines}`, lines are numbered from 1"
+            ));
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            lines: Some(LineRange { start, end }),
+        })
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, ToSchema)]
 pub enum TaskStatus {
+    /// The task is waiting for an agent. Fetching a queued task claims it, so it is `Running` afterwards.
     Queued,
     Running,
     Completed,
     Failed,
+    /// The user cancelled the task. The agent should stop, keep its work and report a failure.
+    Cancelled,
+    /// A status introduced by a newer runtime.
+    #[serde(other)]
+    Unknown,
 }
 
-#[derive(Deserialize, Serialize)]
+/// The status of the task, without the rest of it.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct TaskState {
+    pub status: TaskStatus,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct TaskComplete {
     pub description: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct TaskFailure {
     pub reason: Option<TaskFailureReason>,
     pub description: String,
 }
 
-#[derive(Deserialize, Serialize, Copy, Clone)]
+#[derive(Deserialize, Serialize, Copy, Clone, ToSchema)]
 pub enum TaskFailureReason {
     /// The agent failed to complete the task due to technical problems unrelated to the task itself
     TechnicalIssues,
@@ -40,4 +165,59 @@ pub enum TaskFailureReason {
     /// There were no fundamental technical issues and the task was valid, but the agent still failed
     /// to complete the task because it did not succeed at task-specific problem-solving.
     ProblemSolving,
+    /// The task was cancelled before the agent completed it
+    Cancelled,
+    /// A reason introduced by a newer agent.
+    #[serde(other)]
+    Unknown,
+}
+
+/// An update on the progress of a running task.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct TaskProgress {
+    /// A short, human-readable description of what the agent is currently doing
+    pub message: String,
+    /// Estimated progress of the task in percent (0-100)
+    #[schema(maximum = 100)]
+    pub percent: Option<u8>,
+    /// The agent's current plan for completing the task
+    #[serde(default)]
+    pub plan: Vec<PlanEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PlanEntry {
+    pub title: String,
+    pub status: PlanEntryStatus,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, ToSchema)]
+pub enum PlanEntryStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// A message the user sent to steer the agent while it works on its task.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct TaskMessage {
+    /// Sequence number of the message, starting at 1
+    pub id: u64,
+    pub message: String,
+}
+
+/// Query for fetching the task.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskQuery {
+    /// Identifies the agent that claims a queued task. Fetching the task again with the same
+    /// claim, e.g. in a retried request, returns it as `Queued` again.
+    pub claim: Option<String>,
+}
+
+/// Query for messages that were sent after the message with the given ID.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskMessagesQuery {
+    pub after: Option<u64>,
 }
@@ -0,0 +1,85 @@
+//! Tests for parsing a `FileReference` and task failures.
+#![cfg(test)]
+
+use super::{FileReference, LineRange, TaskFailure, TaskFailureReason};
+
+fn parse(s: &str) -> Result<FileReference, String> {
+    s.parse()
+}
+
+fn reference(path: &str, lines: Option<(u32, u32)>) -> FileReference {
+    FileReference {
+        path: path.to_owned(),
+        lines: lines.map(|(start, end)| LineRange { start, end }),
+    }
+}
+
+#[test]
+fn path_only() {
+    assert_eq!(parse("src/main.rs"), Ok(reference("src/main.rs", None)));
+}
+
+#[test]
+fn single_line() {
+    assert_eq!(
+        parse("src/main.rs:42"),
+        Ok(reference("src/main.rs", Some((42, 42))))
+    );
+}
+
+#[test]
+fn line_range() {
+    assert_eq!(
+        parse("src/main.rs:3-7"),
+        Ok(reference("src/main.rs", Some((3, 7))))
+    );
+}
+
+#[test]
+fn colons_in_path() {
+    assert_eq!(parse("C:/src/a:b.rs"), Ok(reference("C:/src/a:b.rs", None)));
+    assert_eq!(parse("a:b.rs:12"), Ok(reference("a:b.rs", Some((12, 12)))));
+    assert_eq!(parse("dir:1/file.rs"), Ok(reference("dir:1/file.rs", None)));
+}
+
+#[test]
+fn trailing_colon_is_part_of_the_path() {
+    assert_eq!(parse("notes:"), Ok(reference("notes:", None)));
+}
+
+#[test]
+fn line_zero() {
+    assert!(parse("src/main.rs:0").is_err());
+    assert!(parse("src/main.rs:0-3").is_err());
+}
+
+#[test]
+fn start_after_end() {
+    assert!(parse("src/main.rs:7-3").is_err());
+}
+
+#[test]
+fn malformed_range() {
+    assert!(parse("src/main.rs:-").is_err());
+    assert!(parse("src/main.rs:1-2-3").is_err());
+    assert!(parse("src/main.rs:99999999999").is_err());
+}
+
+#[test]
+fn empty_path() {
+    assert!(parse(":12").is_err());
+}
+
+#[test]
+fn display_round_trip() {
+    for s in ["src/main.rs", "src/main.rs:42", "src/main.rs:3-7"] {
+        assert_eq!(parse(s).unwrap().to_string(), s);
+    }
+}
+
+#[test]
+fn unknown_failure_reasons_are_accepted() {
+    let failure: TaskFailure =
+        serde_json::from_str(r#"{"reason": "OutOfBudget", "description": "Ran out"}"#).unwrap();
+    assert!(matches!(failure.reason, Some(TaskFailureReason::Unknown)));
+}
@@ -1,7 +1,7 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use axum::body::Body;
+use axum::body::{Body, Bytes};
 use axum::extract::Json;
 use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
 use axum::http::{HeaderMap, StatusCode};
@@ -14,8 +14,13 @@ use serde::Serialize;
 use url::Url;
 use uuid::Uuid;
 
+use crate::cache::{CacheKey, ResponseCache};
 use crate::config::{ForwardConfig, ProxyConfig, ProxyError, ProxyResult};
-use crate::requests::CompletionRequest;
+use crate::requests::{CompletionRequest, StreamOptions};
+use usage::{UsageChunkFilter, UsageScanner};
+
+mod tests;
+mod usage;
 
 const ROUNDTRIP_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -85,19 +90,48 @@ async fn completions<C: ProxyConfig + Clone + Send + Sync + 'static>(
         extra_headers,
     } = config.forward(&ctx, &request_payload).await?;
     request_payload.model = model.or(request_payload.model);
+    let cache = cache_lookup(config.as_ref(), &target_url, &request_payload);
 
     if request_payload.stream.unwrap_or(false) {
+        // Ask for the token usage, which is otherwise not reported for streamed completions,
+        // unless the client decided. The client gets the usage only if it asked for it.
+        let strip_usage = request_payload.stream_options.is_none();
+        if strip_usage {
+            request_payload.stream_options = Some(StreamOptions {
+                include_usage: true,
+            });
+        }
         config
             .inspect_interaction(&ctx, &request_payload, None)
             .await;
-        Ok(forward_stream_request(&api_key, target_url, &extra_headers, &request_payload).await)
+        if let Some(resp) = cached_stream_response(&cache, strip_usage, config.as_ref(), &ctx).await
+        {
+            return Ok(resp);
+        }
+        Ok(forward_stream_request(
+            &api_key,
+            target_url,
+            &extra_headers,
+            &request_payload,
+            strip_usage,
+            cache,
+            (config, ctx),
+        )
+        .await)
     } else {
+        if let Some((resp, response_json)) = cached_non_stream_response(&cache).await {
+            config
+                .inspect_interaction(&ctx, &request_payload, response_json)
+                .await;
+            return Ok(resp);
+        }
         let (mut resp, mut response_json) =
             forward_non_stream_request(&api_key, target_url, &extra_headers, &request_payload)
                 .await?;
         if let Some(response_json) = &mut response_json {
             patch_response_id(response_json);
             let body = response_json.to_string();
+            store_in_cache(&cache, body.as_bytes()).await;
             *resp.body_mut() = Body::from(body);
         }
         config
@@ -126,6 +160,7 @@ async fn responses<C: ProxyConfig + Clone + Send + Sync + 'static>(
     if let Some(model) = model {
         set_model(&mut request_payload, model)?;
     }
+    let cache = cache_lookup(config.as_ref(), &target_url, &request_payload);
 
     if request_payload
         .get("stream")
@@ -135,14 +170,33 @@ async fn responses<C: ProxyConfig + Clone + Send + Sync + 'static>(
         config
             .inspect_responses_interaction(&ctx, &request_payload, None)
             .await;
-        Ok(forward_stream_request(&api_key, target_url, &extra_headers, &request_payload).await)
+        if let Some(resp) = cached_stream_response(&cache, false, config.as_ref(), &ctx).await {
+            return Ok(resp);
+        }
+        Ok(forward_stream_request(
+            &api_key,
+            target_url,
+            &extra_headers,
+            &request_payload,
+            false,
+            cache,
+            (config, ctx),
+        )
+        .await)
     } else {
+        if let Some((resp, response_json)) = cached_non_stream_response(&cache).await {
+            config
+                .inspect_responses_interaction(&ctx, &request_payload, response_json)
+                .await;
+            return Ok(resp);
+        }
         let (mut resp, mut response_json) =
             forward_non_stream_request(&api_key, target_url, &extra_headers, &request_payload)
                 .await?;
         if let Some(response_json) = &mut response_json {
             patch_response_id(response_json);
             let body = response_json.to_string();
+            store_in_cache(&cache, body.as_bytes()).await;
             *resp.body_mut() = Body::from(body);
         }
         config
@@ -252,11 +306,19 @@ async fn forward_get_request(
 }
 
 /// Forward a streaming (SSE) request.
-async fn forward_stream_request(
+///
+/// If a cache entry is given, the complete event stream is stored once the upstream stream ends
+/// without errors. The token usage reported in the stream is passed to
+/// [`ProxyConfig::inspect_stream_usage`] at the end. If `strip_usage` is set, the usage-only
+/// chunk is not passed on to the client.
+async fn forward_stream_request<C: ProxyConfig>(
     api_key: &str,
     target_url: Url,
     extra_headers: &HeaderMap,
     request_payload: &(impl Serialize + ?Sized),
+    strip_usage: bool,
+    cache: Option<(ResponseCache, CacheKey)>,
+    (config, ctx): (Arc<C>, C::Context),
 ) -> Response {
     let client = create_reqwest_client();
     let mut req_builder = client
@@ -295,19 +357,119 @@ async fn forward_stream_request(
         };
     }
 
-    let byte_stream = resp.bytes_stream().map(|chunk| match chunk {
-        Ok(c) => Ok(c),
-        Err(err) => {
-            log::error!("Error reading SSE chunk: {:?}", err);
-            Err(std::io::Error::other(err))
+    // Collects the streamed bytes for the cache; reset to `None` if the stream fails.
+    let recorded = Arc::new(Mutex::new(cache.as_ref().map(|_| Vec::new())));
+    let usage = Arc::new(Mutex::new(UsageScanner::default()));
+    let filter = Arc::new(Mutex::new(strip_usage.then(UsageChunkFilter::default)));
+
+    let byte_stream = resp.bytes_stream().map({
+        let recorded = recorded.clone();
+        let usage = usage.clone();
+        let filter = filter.clone();
+        move |chunk| {
+            let mut recorded = recorded.lock().unwrap();
+            match chunk {
+                Ok(c) => {
+                    if let Some(buffer) = recorded.as_mut() {
+                        buffer.extend_from_slice(&c);
+                    }
+                    usage.lock().unwrap().feed(&c);
+                    match filter.lock().unwrap().as_mut() {
+                        Some(filter) => Ok(Bytes::from(filter.filter(&c))),
+                        None => Ok(c),
+                    }
+                }
+                Err(err) => {
+                    log::error!("Error reading SSE chunk: {:?}", err);
+                    *recorded = None;
+                    Err(std::io::Error::other(err))
+                }
+            }
         }
     });
 
+    let store = futures_util::stream::once(async move {
+        let bytes = recorded.lock().unwrap().take();
+        if let (Some((cache, key)), Some(bytes)) = (cache, bytes) {
+            cache.put(&key, &bytes).await;
+        }
+        let usage = std::mem::take(&mut *usage.lock().unwrap()).finish();
+        if let Some(usage) = usage {
+            config.inspect_stream_usage(&ctx, usage).await;
+        }
+        let rest = filter.lock().unwrap().take().map(UsageChunkFilter::finish);
+        rest.filter(|rest| !rest.is_empty())
+            .map(|rest| Ok(Bytes::from(rest)))
+    })
+    .filter_map(|chunk: Option<Result<Bytes, std::io::Error>>| async move { chunk });
+
+    stream_response(Body::from_stream(byte_stream.chain(store)))
+}
+
+/// Compute the cache key for a request if caching is enabled and the request is eligible.
+fn cache_lookup<C: ProxyConfig>(
+    config: &C,
+    target_url: &Url,
+    request_payload: &impl Serialize,
+) -> Option<(ResponseCache, CacheKey)> {
+    let cache = config.response_cache()?;
+    let body = serde_json::to_value(request_payload).ok()?;
+    let key = ResponseCache::key(target_url, &body)?;
+    Some((cache.clone(), key))
+}
+
+/// Replay a cached event stream, without the usage-only chunk if `strip_usage` is set.
+async fn cached_stream_response<C: ProxyConfig>(
+    cache: &Option<(ResponseCache, CacheKey)>,
+    strip_usage: bool,
+    config: &C,
+    ctx: &C::Context,
+) -> Option<Response> {
+    let (cache, key) = cache.as_ref()?;
+    let bytes = cache.get(key).await?;
+    log::debug!("Serving streaming response from cache");
+    let mut usage = UsageScanner::default();
+    usage.feed(&bytes);
+    if let Some(usage) = usage.finish() {
+        config.inspect_stream_usage(ctx, usage).await;
+    }
+    if strip_usage {
+        let mut filter = UsageChunkFilter::default();
+        let mut filtered = filter.filter(&bytes);
+        filtered.extend(filter.finish());
+        return Some(stream_response(Body::from(filtered)));
+    }
+    Some(stream_response(Body::from(bytes)))
+}
+
+/// Serve a cached non-streaming response along with its parsed JSON body.
+async fn cached_non_stream_response(
+    cache: &Option<(ResponseCache, CacheKey)>,
+) -> Option<(Response, Option<serde_json::Value>)> {
+    let (cache, key) = cache.as_ref()?;
+    let bytes = cache.get(key).await?;
+    log::debug!("Serving response from cache");
+    let response_json = serde_json::from_slice(&bytes).ok();
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(bytes))
+        .unwrap();
+    Some((response, response_json))
+}
+
+async fn store_in_cache(cache: &Option<(ResponseCache, CacheKey)>, bytes: &[u8]) {
+    if let Some((cache, key)) = cache {
+        cache.put(key, bytes).await;
+    }
+}
+
+fn stream_response(body: Body) -> Response {
     Response::builder()
         .status(StatusCode::OK)
         .header(CONTENT_TYPE, "text/event-stream")
         .header(CACHE_CONTROL, "no-cache")
-        .body(Body::from_stream(byte_stream))
+        .body(body)
         .unwrap()
 }
 
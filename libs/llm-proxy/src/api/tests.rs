@@ -0,0 +1,243 @@
+#![cfg(test)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::body::{Body, Bytes};
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::{Json, Router};
+use futures_util::stream;
+use url::Url;
+
+use crate::cache::ResponseCache;
+use crate::config::{ForwardConfig, ProxyConfig, ProxyResult};
+use crate::requests::CompletionRequest;
+
+use super::usage::{UsageChunkFilter, UsageScanner};
+
+const EVENTS: &str = "data: {\"choices\":[]}\n\n\
+    data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":5}}\n\n\
+    data: [DONE]\n\n";
+/// `EVENTS` without the usage-only chunk, for clients that did not ask for it.
+const EVENTS_WITHOUT_USAGE: &str = "data: {\"choices\":[]}\n\ndata: [DONE]\n\n";
+
+#[derive(Clone)]
+struct TestConfig {
+    upstream: Url,
+    cache: ResponseCache,
+    usage: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+#[async_trait]
+impl ProxyConfig for TestConfig {
+    type Context = ();
+
+    async fn extract_context(&self, _headers: &HeaderMap) -> ProxyResult<()> {
+        Ok(())
+    }
+
+    async fn forward(&self, _ctx: &(), _req: &CompletionRequest) -> ProxyResult<ForwardConfig> {
+        Ok(ForwardConfig {
+            api_key: "key".to_owned(),
+            target_url: self.upstream.clone(),
+            model: None,
+            extra_headers: HeaderMap::new(),
+        })
+    }
+
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        Some(&self.cache)
+    }
+
+    async fn inspect_interaction(
+        &self,
+        _ctx: &(),
+        _req: &CompletionRequest,
+        _response: Option<serde_json::Value>,
+    ) {
+    }
+
+    async fn inspect_stream_usage(&self, _ctx: &(), usage: serde_json::Value) {
+        self.usage.lock().unwrap().push(usage);
+    }
+}
+
+async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// An upstream that streams `EVENTS`, failing halfway through if `fail` is set.
+async fn upstream(calls: Arc<AtomicU32>, fail: bool) -> Url {
+    let router = Router::new().route(
+        "/v1/chat/completions",
+        post(move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let (first, second) = EVENTS.split_at(20);
+                let chunks = vec![
+                    Ok(Bytes::from(first)),
+                    if fail {
+                        Err(std::io::Error::other("connection lost"))
+                    } else {
+                        Ok(Bytes::from(second))
+                    },
+                ];
+                Body::from_stream(stream::iter(chunks))
+            }
+        }),
+    );
+    format!("{}/v1/chat/completions", serve(router).await)
+        .parse()
+        .unwrap()
+}
+
+async fn proxy(upstream: Url, cache_dir: &std::path::Path) -> (String, TestConfig) {
+    let config = TestConfig {
+        upstream,
+        cache: ResponseCache::new(cache_dir),
+        usage: Arc::default(),
+    };
+    let url = serve(Router::new().nest("/api", crate::scope(config.clone()))).await;
+    (url, config)
+}
+
+async fn stream_completion(proxy: &str) -> reqwest::Result<String> {
+    stream_completion_with(proxy, None).await
+}
+
+async fn stream_completion_with(
+    proxy: &str,
+    include_usage: Option<bool>,
+) -> reqwest::Result<String> {
+    let mut body = serde_json::json!({
+        "model": "m",
+        "stream": true,
+        "temperature": 0,
+        "messages": [{"role": "user", "content": "hi"}],
+    });
+    if let Some(include_usage) = include_usage {
+        body["stream_options"] = serde_json::json!({ "include_usage": include_usage });
+    }
+    reqwest::Client::new()
+        .post(format!("{proxy}/api/chat/completions"))
+        .json(&body)
+        .send()
+        .await?
+        .text()
+        .await
+}
+
+#[tokio::test]
+async fn streams_are_recorded_and_replayed() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let calls = Arc::new(AtomicU32::new(0));
+    let (proxy, config) = proxy(upstream(calls.clone(), false).await, cache_dir.path()).await;
+
+    assert_eq!(
+        stream_completion(&proxy).await.unwrap(),
+        EVENTS_WITHOUT_USAGE
+    );
+    assert_eq!(
+        stream_completion(&proxy).await.unwrap(),
+        EVENTS_WITHOUT_USAGE
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let usage = serde_json::json!({"prompt_tokens": 3, "completion_tokens": 5});
+    assert_eq!(*config.usage.lock().unwrap(), [usage.clone(), usage]);
+}
+
+#[tokio::test]
+async fn partial_streams_are_not_recorded() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let calls = Arc::new(AtomicU32::new(0));
+    let (proxy, _) = proxy(upstream(calls.clone(), true).await, cache_dir.path()).await;
+
+    for _ in 0..2 {
+        let response = stream_completion(&proxy).await;
+        assert!(!matches!(response.as_deref(), Ok(EVENTS)));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn usage_is_taken_from_the_last_event_that_reports_it() {
+    let mut scanner = UsageScanner::default();
+    let events = "event: response.created\n\
+        data: {\"type\":\"response.created\",\"response\":{\"usage\":null}}\n\n\
+        event: response.completed\n\
+        data: {\"type\":\"response.completed\",\"response\":{\"usage\":{\"input_tokens\":7}}}\n\n";
+    // Events may be split across chunks at any byte.
+    for chunk in events.as_bytes().chunks(7) {
+        scanner.feed(chunk);
+    }
+    assert_eq!(
+        scanner.finish(),
+        Some(serde_json::json!({"input_tokens": 7}))
+    );
+
+    let mut scanner = UsageScanner::default();
+    scanner.feed(b"data: {\"choices\":[],\"usage\":null}\n\ndata: [DONE]");
+    assert_eq!(scanner.finish(), None);
+}
+
+#[tokio::test]
+async fn usage_is_passed_on_if_the_client_asked_for_it() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let calls = Arc::new(AtomicU32::new(0));
+    let (proxy, config) = proxy(upstream(calls.clone(), false).await, cache_dir.path()).await;
+
+    let response = stream_completion_with(&proxy, Some(true)).await.unwrap();
+    assert_eq!(response, EVENTS);
+    assert_eq!(config.usage.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn clients_may_decline_the_usage() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let router = Router::new().route(
+        "/v1/chat/completions",
+        post({
+            let requests = requests.clone();
+            move |Json(request): Json<serde_json::Value>| async move {
+                requests.lock().unwrap().push(request);
+                EVENTS_WITHOUT_USAGE
+            }
+        }),
+    );
+    let upstream = format!("{}/v1/chat/completions", serve(router).await);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let (proxy, _) = proxy(upstream.parse().unwrap(), cache_dir.path()).await;
+
+    let response = stream_completion_with(&proxy, Some(false)).await.unwrap();
+    assert_eq!(response, EVENTS_WITHOUT_USAGE);
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0]["stream_options"],
+        serde_json::json!({ "include_usage": false })
+    );
+}
+
+#[test]
+fn usage_chunk_filter_drops_only_the_usage_chunk() {
+    let mut filter = UsageChunkFilter::default();
+    let mut filtered = Vec::new();
+    // Events may be split across chunks at any byte.
+    for chunk in EVENTS.as_bytes().chunks(7) {
+        filtered.extend(filter.filter(chunk));
+    }
+    filtered.extend(filter.finish());
+    assert_eq!(String::from_utf8(filtered).unwrap(), EVENTS_WITHOUT_USAGE);
+
+    // A final chunk that reports the usage along with choices is kept.
+    let events = "data: {\"choices\":[{\"delta\":{}}],\"usage\":{\"prompt_tokens\":1}}\n\n";
+    let mut filter = UsageChunkFilter::default();
+    let mut filtered = filter.filter(events.as_bytes());
+    filtered.extend(filter.finish());
+    assert_eq!(String::from_utf8(filtered).unwrap(), events);
+}
@@ -0,0 +1,96 @@
+use serde_json::Value;
+
+/// Picks the token usage out of a streamed (SSE) response as it passes through.
+///
+/// Chat completions report usage in a final chunk with a `usage` object (if the request asked for
+/// it via `stream_options.include_usage`), the Responses API in the `response.completed` event.
+#[derive(Default)]
+pub(crate) struct UsageScanner {
+    line: Vec<u8>,
+    usage: Option<Value>,
+}
+
+impl UsageScanner {
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == b'\n' {
+                let line = std::mem::take(&mut self.line);
+                self.scan_line(&line);
+            } else {
+                self.line.push(byte);
+            }
+        }
+    }
+
+    /// The usage of the last event that reported one.
+    pub(crate) fn finish(mut self) -> Option<Value> {
+        let line = std::mem::take(&mut self.line);
+        self.scan_line(&line);
+        self.usage
+    }
+
+    fn scan_line(&mut self, line: &[u8]) {
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return;
+        };
+        let Ok(event) = serde_json::from_slice::<Value>(data.trim_ascii()) else {
+            return;
+        };
+        let usage = match event.get("type").and_then(Value::as_str) {
+            Some("response.completed") => event.get("response").and_then(|r| r.get("usage")),
+            _ => event.get("usage"),
+        };
+        if let Some(usage) = usage.filter(|usage| usage.is_object()) {
+            self.usage = Some(usage.clone());
+        }
+    }
+}
+
+/// Drops the usage-only chunk from a streamed chat completion, for clients that did not ask for
+/// it. Events are passed on whole once their terminating blank line arrived.
+#[derive(Default)]
+pub(crate) struct UsageChunkFilter {
+    event: Vec<u8>,
+}
+
+impl UsageChunkFilter {
+    /// The complete events in `bytes` and before, except for a usage-only chunk.
+    pub(crate) fn filter(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut filtered = Vec::new();
+        for &byte in bytes {
+            self.event.push(byte);
+            if self.event.ends_with(b"\n\n") || self.event.ends_with(b"\r\n\r\n") {
+                let event = std::mem::take(&mut self.event);
+                if !is_usage_chunk(&event) {
+                    filtered.extend_from_slice(&event);
+                }
+            }
+        }
+        filtered
+    }
+
+    /// The rest of a stream that did not end with a blank line.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        if is_usage_chunk(&self.event) {
+            Vec::new()
+        } else {
+            self.event
+        }
+    }
+}
+
+/// Whether an event is a chunk with a `usage` object and no choices.
+fn is_usage_chunk(event: &[u8]) -> bool {
+    event
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| line.strip_prefix(b"data:"))
+        .any(|data| {
+            serde_json::from_slice::<Value>(data.trim_ascii()).is_ok_and(|chunk| {
+                chunk.get("usage").is_some_and(Value::is_object)
+                    && chunk
+                        .get("choices")
+                        .and_then(Value::as_array)
+                        .is_some_and(Vec::is_empty)
+            })
+        })
+}
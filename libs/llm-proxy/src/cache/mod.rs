@@ -0,0 +1,251 @@
+//! A content-addressed, on-disk cache for upstream LLM responses.
+//!
+//! Only requests that are expected to be deterministic are cached, i.e. requests with a
+//! temperature of `0` or a fixed `seed`. The cache key is derived from the target URL and the
+//! normalized request body (which includes the target model and whether the response is streamed).
+//! Streaming responses are cached as the raw SSE byte stream and replayed as-is.
+
+use std::fs::FileTimes;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+mod tests;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const DEFAULT_MAX_SIZE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// On-disk response cache.
+///
+/// Entries expire after the configured time-to-live, counted from when they were stored. Once the
+/// total size of the cache exceeds the configured limit, the least recently used entries are
+/// evicted. Cache hits update the access time of an entry, while its modification time is the
+/// time it was stored.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_size_bytes: u64,
+    /// Total size of the entries as of the last scan of the directory, plus what was stored
+    /// since. `None` until the directory is scanned for the first time.
+    total_size: Arc<Mutex<Option<u64>>>,
+}
+
+/// Identifies a cache entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheKey(String);
+
+impl ResponseCache {
+    /// Create a cache that stores its entries in the given directory.
+    /// The directory is created on first use.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: DEFAULT_TTL,
+            max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
+            total_size: Arc::default(),
+        }
+    }
+
+    /// Set the time after which cache entries are no longer served.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the maximum total size of all cache entries.
+    pub fn max_size_bytes(mut self, max_size_bytes: u64) -> Self {
+        self.max_size_bytes = max_size_bytes;
+        self
+    }
+
+    /// Compute the cache key for a request that is about to be forwarded to `target_url`.
+    ///
+    /// Returns `None` if the request is not eligible for caching.
+    pub(crate) fn key(target_url: &Url, body: &serde_json::Value) -> Option<CacheKey> {
+        if !is_deterministic(body) {
+            return None;
+        }
+
+        let mut normalized = String::new();
+        write_canonical_json(body, &mut normalized);
+
+        let mut hasher = Sha256::new();
+        hasher.update(target_url.as_str().as_bytes());
+        hasher.update([0]);
+        hasher.update(normalized.as_bytes());
+        let digest = hasher.finalize();
+
+        Some(CacheKey(
+            digest.iter().map(|byte| format!("{byte:02x}")).collect(),
+        ))
+    }
+
+    /// Look up a cache entry, ignoring entries that have expired.
+    pub(crate) async fn get(&self, key: &CacheKey) -> Option<Bytes> {
+        let path = self.entry_path(key);
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        if self.is_expired(&metadata) {
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                self.count(0, metadata.len());
+            }
+            return None;
+        }
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => {
+                if let Err(err) = touch(&path).await {
+                    log::debug!("Failed to touch cache entry {}: {:?}", path.display(), err);
+                }
+                Some(Bytes::from(bytes))
+            }
+            Err(err) => {
+                log::warn!("Failed to read cache entry {}: {:?}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Store a cache entry and evict entries if the cache exceeds its size limit.
+    pub(crate) async fn put(&self, key: &CacheKey, bytes: &[u8]) {
+        if bytes.len() as u64 > self.max_size_bytes {
+            return;
+        }
+        let replaced = match self.write_entry(key, bytes).await {
+            Ok(replaced) => replaced,
+            Err(err) => {
+                log::warn!("Failed to write cache entry: {:?}", err);
+                return;
+            }
+        };
+        let total_size = self.count(bytes.len() as u64, replaced);
+        if total_size.is_none_or(|total_size| total_size > self.max_size_bytes) {
+            if let Err(err) = self.evict().await {
+                log::warn!("Failed to evict cache entries: {:?}", err);
+            }
+        }
+    }
+
+    /// Write an entry and return the size of the entry it replaced, if any.
+    async fn write_entry(&self, key: &CacheKey, bytes: &[u8]) -> std::io::Result<u64> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Write to a temporary file first so that concurrent readers never see partial entries.
+        let tmp_path = self
+            .dir
+            .join(format!("{}.tmp-{}", key.0, uuid::Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, bytes).await?;
+        let path = self.entry_path(key);
+        let replaced = tokio::fs::metadata(&path)
+            .await
+            .map_or(0, |metadata| metadata.len());
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(replaced)
+    }
+
+    /// Update the total size for entries that were added or removed. Returns the new total, or
+    /// `None` if the directory was not scanned yet.
+    fn count(&self, added: u64, removed: u64) -> Option<u64> {
+        let mut total_size = self.total_size.lock().unwrap();
+        *total_size = total_size.map(|total_size| (total_size + added).saturating_sub(removed));
+        *total_size
+    }
+
+    /// Remove expired entries and then the least recently used ones until the cache is within
+    /// its size limit.
+    async fn evict(&self) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total_size = 0u64;
+
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            // Skip entries that are still being written.
+            if !metadata.is_file() || entry.file_name().to_string_lossy().contains(".tmp-") {
+                continue;
+            }
+            if self.is_expired(&metadata) {
+                tokio::fs::remove_file(entry.path()).await?;
+                continue;
+            }
+            let accessed = metadata.accessed().unwrap_or(SystemTime::UNIX_EPOCH);
+            total_size += metadata.len();
+            entries.push((accessed, metadata.len(), entry.path()));
+        }
+
+        entries.sort_by_key(|(accessed, _, _)| *accessed);
+        for (_, size, path) in entries {
+            if total_size <= self.max_size_bytes {
+                break;
+            }
+            tokio::fs::remove_file(&path).await?;
+            total_size -= size;
+        }
+
+        *self.total_size.lock().unwrap() = Some(total_size);
+        Ok(())
+    }
+
+    fn is_expired(&self, metadata: &std::fs::Metadata) -> bool {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age > self.ttl)
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(&key.0)
+    }
+}
+
+/// Mark an entry as used now. Its access time is set explicitly, since file systems are often
+/// mounted with `noatime` or `relatime`.
+async fn touch(path: &Path) -> std::io::Result<()> {
+    let file = tokio::fs::File::open(path).await?.into_std().await;
+    file.set_times(FileTimes::new().set_accessed(SystemTime::now()))
+}
+
+/// Requests are considered deterministic if they use a temperature of zero or a fixed seed.
+fn is_deterministic(body: &serde_json::Value) -> bool {
+    let zero_temperature = body
+        .get("temperature")
+        .and_then(|v| v.as_f64())
+        .is_some_and(|temperature| temperature == 0.0);
+    let fixed_seed = body.get("seed").is_some_and(|seed| !seed.is_null());
+    zero_temperature || fixed_seed
+}
+
+/// Serialize JSON with object keys in sorted order, so that semantically equal
+/// requests map to the same cache key.
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
@@ -0,0 +1,109 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use serde_json::json;
+use url::Url;
+
+use super::{is_deterministic, ResponseCache};
+
+fn url() -> Url {
+    "https://api.example.com/v1/chat/completions"
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn key_ignores_order_of_object_keys() {
+    let a =
+        json!({"model": "m", "temperature": 0, "messages": [{"role": "user", "content": "hi"}]});
+    let b =
+        json!({"messages": [{"content": "hi", "role": "user"}], "temperature": 0, "model": "m"});
+    assert_eq!(
+        ResponseCache::key(&url(), &a),
+        ResponseCache::key(&url(), &b)
+    );
+}
+
+#[test]
+fn key_depends_on_body_and_url() {
+    let a = json!({"model": "m", "temperature": 0, "stream": false});
+    let b = json!({"model": "m", "temperature": 0, "stream": true});
+    assert_ne!(
+        ResponseCache::key(&url(), &a),
+        ResponseCache::key(&url(), &b)
+    );
+
+    let other_url = "https://other.example.com/v1/chat/completions"
+        .parse()
+        .unwrap();
+    assert_ne!(
+        ResponseCache::key(&url(), &a),
+        ResponseCache::key(&other_url, &a)
+    );
+}
+
+#[test]
+fn only_deterministic_requests_are_cached() {
+    assert!(is_deterministic(&json!({"temperature": 0})));
+    assert!(is_deterministic(&json!({"temperature": 0.0})));
+    assert!(is_deterministic(&json!({"temperature": 1, "seed": 42})));
+    assert!(!is_deterministic(&json!({"temperature": 0.7})));
+    assert!(!is_deterministic(&json!({"seed": null})));
+    assert!(!is_deterministic(&json!({})));
+    assert_eq!(ResponseCache::key(&url(), &json!({"model": "m"})), None);
+}
+
+#[tokio::test]
+async fn entries_expire_after_ttl() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::new(dir.path()).ttl(Duration::from_millis(50));
+    let key = ResponseCache::key(&url(), &json!({"temperature": 0})).unwrap();
+
+    cache.put(&key, b"response").await;
+    assert_eq!(cache.get(&key).await.as_deref(), Some(&b"response"[..]));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(cache.get(&key).await, None);
+}
+
+#[tokio::test]
+async fn oldest_entries_are_evicted_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::new(dir.path()).max_size_bytes(10);
+    let old = ResponseCache::key(&url(), &json!({"seed": 1})).unwrap();
+    let new = ResponseCache::key(&url(), &json!({"seed": 2})).unwrap();
+    let too_large = ResponseCache::key(&url(), &json!({"seed": 3})).unwrap();
+
+    cache.put(&old, b"123456").await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    cache.put(&new, b"abcdef").await;
+    assert_eq!(cache.get(&old).await, None);
+    assert_eq!(cache.get(&new).await.as_deref(), Some(&b"abcdef"[..]));
+
+    // Entries larger than the whole cache are not stored and evict nothing.
+    cache.put(&too_large, b"0123456789ab").await;
+    assert_eq!(cache.get(&too_large).await, None);
+    assert!(cache.get(&new).await.is_some());
+}
+
+#[tokio::test]
+async fn least_recently_used_entries_are_evicted_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::new(dir.path()).max_size_bytes(12);
+    let used = ResponseCache::key(&url(), &json!({"seed": 1})).unwrap();
+    let unused = ResponseCache::key(&url(), &json!({"seed": 2})).unwrap();
+    let new = ResponseCache::key(&url(), &json!({"seed": 3})).unwrap();
+
+    cache.put(&used, b"123456").await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    cache.put(&unused, b"abcdef").await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(cache.get(&used).await.is_some());
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    cache.put(&new, b"ghijkl").await;
+
+    assert_eq!(cache.get(&unused).await, None);
+    assert!(cache.get(&used).await.is_some());
+    assert!(cache.get(&new).await.is_some());
+}
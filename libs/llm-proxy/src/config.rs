@@ -3,6 +3,7 @@ use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use url::Url;
 
+use crate::cache::ResponseCache;
 use crate::requests::CompletionRequest;
 
 #[async_trait]
@@ -38,6 +39,12 @@ pub trait ProxyConfig: Send + Sync + 'static {
         ))
     }
 
+    /// Optionally provide a cache for responses to deterministic requests.
+    /// Responses are not cached if `None` is returned.
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        None
+    }
+
     /// Optionally handle the interaction after the reqest has been forwarded.
     /// In a streaming scenario, the response will be `None`.
     async fn inspect_interaction(
@@ -56,6 +63,10 @@ pub trait ProxyConfig: Send + Sync + 'static {
         _response: Option<serde_json::Value>,
     ) {
     }
+
+    /// Optionally handle the token usage reported at the end of a streamed response.
+    /// `usage` is the `usage` object of the chat completions or the Responses API.
+    async fn inspect_stream_usage(&self, _ctx: &Self::Context, _usage: serde_json::Value) {}
 }
 
 #[derive(Debug)]
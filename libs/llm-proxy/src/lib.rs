@@ -1,7 +1,9 @@
 mod api;
+mod cache;
 mod config;
 mod requests;
 
 pub use api::scope;
+pub use cache::ResponseCache;
 pub use config::{ForwardConfig, ProxyConfig, ProxyError, ProxyResult};
 pub use requests::CompletionRequest;
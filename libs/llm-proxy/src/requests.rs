@@ -35,6 +35,10 @@ pub struct CompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 
+    /// Options for streaming responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+
     // ------------------------------------------------------------------
     // See LLM Parameters (openrouter.ai/docs/parameters)
     // ------------------------------------------------------------------
@@ -121,6 +125,13 @@ pub struct ResponseFormat {
     pub response_type: String,
 }
 
+/// Matches `stream_options?: { include_usage: boolean }`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Send a final chunk with the token usage of the whole request
+    pub include_usage: bool,
+}
+
 /// Matches `stop?: string | string[]`.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]